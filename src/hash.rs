@@ -0,0 +1,314 @@
+//! Computing [hashes](https://spec.scuttlebutt.nz/feed/datamodel.html#legacy-hash-computation)
+//! of legacy data. Only available with the `hash` feature (or its alias `sha256`).
+
+use std::io;
+
+use sha2::{Digest, Sha256};
+
+use super::to_weird_encoding;
+
+/// An `io::Write` that feeds the
+/// [weird encoding](https://spec.scuttlebutt.nz/feed/datamodel.html#legacy-hash-computation)
+/// of the utf8 written to it into a sha256 hash, so that data can be hashed while it is being
+/// serialized.
+///
+/// A utf8 sequence may be split across several writes. A write never consumes a byte that
+/// would make the data written so far invalid utf8: it stops before that byte, or fails with
+/// an `io::ErrorKind::InvalidData` error if it is the first byte of the write.
+///
+/// ```
+/// use ssb_legacy_msg_data::hash::WeirdEncodingHasher;
+/// use ssb_legacy_msg_data::json::{from_slice, to_writer};
+/// use ssb_legacy_msg_data::value::Value;
+///
+/// let value = from_slice::<Value>(r#"{"type": "post", "text": "ö"}"#.as_bytes()).unwrap();
+/// let mut hasher = WeirdEncodingHasher::new();
+/// to_writer(&mut hasher, &value, false).unwrap();
+///
+/// assert_eq!(hasher.len(), 35);
+/// let digest: [u8; 32] = hasher.finalize().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct WeirdEncodingHasher {
+    hasher: Sha256,
+    len: usize,
+    // The bytes of an incomplete utf8 sequence.
+    pending: [u8; 4],
+    pending_len: usize,
+    // Weird encoding bytes that have not been fed into `hasher` yet.
+    buf: [u8; 256],
+    buffered: usize,
+}
+
+impl Default for WeirdEncodingHasher {
+    fn default() -> WeirdEncodingHasher {
+        WeirdEncodingHasher {
+            hasher: Sha256::default(),
+            len: 0,
+            pending: [0; 4],
+            pending_len: 0,
+            buf: [0; 256],
+            buffered: 0,
+        }
+    }
+}
+
+impl WeirdEncodingHasher {
+    /// Creates a new hasher that has not been fed any data yet.
+    pub fn new() -> WeirdEncodingHasher {
+        WeirdEncodingHasher::default()
+    }
+
+    /// The number of bytes fed into the hash so far, i.e. the
+    /// [length](https://spec.scuttlebutt.nz/feed/datamodel.html#legacy-length-computation) of
+    /// the data written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no data has been hashed so far.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Consumes the hasher and returns the sha256 digest of all data written to it.
+    ///
+    /// Fails with an `io::ErrorKind::InvalidData` error if the data written ends with an
+    /// incomplete utf8 sequence.
+    pub fn finalize(mut self) -> Result<[u8; 32], io::Error> {
+        if self.pending_len != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "incomplete utf8 sequence",
+            ));
+        }
+
+        self.hasher.update(&self.buf[..self.buffered]);
+        let mut digest = [0; 32];
+        digest.copy_from_slice(&self.hasher.finalize());
+        Ok(digest)
+    }
+
+    fn update(&mut self, c: char) {
+        let mut units = [0; 2];
+        for unit in c.encode_utf16(&mut units) {
+            if self.buffered == self.buf.len() {
+                self.hasher.update(&self.buf[..]);
+                self.buffered = 0;
+            }
+
+            self.buf[self.buffered] = *unit as u8;
+            self.buffered += 1;
+            self.len += 1;
+        }
+    }
+
+    // Whether `byte` may follow the pending bytes in valid utf8.
+    fn accepts(&self, byte: u8) -> bool {
+        match (self.pending_len, self.pending[0]) {
+            (0, _) => matches!(byte, 0x00..=0x7F | 0xC2..=0xF4),
+            // Exclude overlong encodings, surrogates and code points above U+10FFFF.
+            (1, 0xE0) => matches!(byte, 0xA0..=0xBF),
+            (1, 0xED) => matches!(byte, 0x80..=0x9F),
+            (1, 0xF0) => matches!(byte, 0x90..=0xBF),
+            (1, 0xF4) => matches!(byte, 0x80..=0x8F),
+            _ => matches!(byte, 0x80..=0xBF),
+        }
+    }
+}
+
+impl io::Write for WeirdEncodingHasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for (i, &byte) in buf.iter().enumerate() {
+            if !self.accepts(byte) {
+                if i == 0 {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid utf8"));
+                } else {
+                    return Ok(i);
+                }
+            }
+
+            self.pending[self.pending_len] = byte;
+            self.pending_len += 1;
+
+            let expected = match self.pending[0] {
+                0x00..=0x7F => 1,
+                0xC2..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                _ => 4,
+            };
+
+            if self.pending_len == expected {
+                self.pending_len = 0;
+                match std::str::from_utf8(&self.pending[..expected]) {
+                    Ok(s) => {
+                        let c = s.chars().next().unwrap();
+                        self.update(c);
+                    }
+                    Err(_) => unreachable!("We only accepted valid utf8"),
+                }
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Computes the sha256 hash of the
+/// [weird encoding](https://spec.scuttlebutt.nz/feed/datamodel.html#legacy-hash-computation)
+/// of a message, i.e. the hash that identifies a legacy message. `msg` must be the message in
+/// the [signing encoding](https://spec.scuttlebutt.nz/feed/datamodel.html#signing-encoding),
+/// including its signature.
+///
+/// ```
+/// use ssb_legacy_msg_data::hash::message_hash;
+///
+/// let digest: [u8; 32] = message_hash("{\n  \"type\": \"post\"\n}");
+/// ```
+pub fn message_hash(msg: &str) -> [u8; 32] {
+    hash_and_length(msg).0
+}
+
+/// Computes both the [hash](fn.message_hash.html) and the
+/// [length](https://spec.scuttlebutt.nz/feed/datamodel.html#legacy-length-computation) of a
+/// message in a single pass over it. The length is the one to check against the maximum size
+/// of a message.
+///
+/// ```
+/// use ssb_legacy_msg_data::hash::{hash_and_length, message_hash};
+/// use ssb_legacy_msg_data::legacy_length;
+///
+/// let msg = "{\n  \"type\": \"post\"\n}";
+/// let (digest, len) = hash_and_length(msg);
+/// assert_eq!(digest, message_hash(msg));
+/// assert_eq!(len, legacy_length(msg));
+/// ```
+pub fn hash_and_length(msg: &str) -> ([u8; 32], usize) {
+    let mut hasher = Sha256::new();
+    let mut len = 0;
+
+    // Feed the bytes in chunks rather than one by one.
+    let mut buf = [0; 256];
+    let mut buffered = 0;
+    for byte in to_weird_encoding(msg) {
+        buf[buffered] = byte;
+        buffered += 1;
+        len += 1;
+
+        if buffered == buf.len() {
+            hasher.update(&buf[..]);
+            buffered = 0;
+        }
+    }
+    hasher.update(&buf[..buffered]);
+
+    let mut digest = [0; 32];
+    digest.copy_from_slice(&hasher.finalize());
+    (digest, len)
+}
+
+#[test]
+fn test_weird_encoding_hasher() {
+    use std::io::Write;
+
+    let s = "a\u{00f6}\u{20ac}\u{1f600}\n";
+
+    let mut expected = Sha256::new();
+    let weird: Vec<u8> = to_weird_encoding(s).collect();
+    expected.update(&weird);
+
+    // Feed the data one byte at a time, splitting all multi-byte sequences.
+    let mut hasher = WeirdEncodingHasher::new();
+    for byte in s.bytes() {
+        hasher.write_all(&[byte]).unwrap();
+    }
+
+    assert_eq!(hasher.len(), weird.len());
+    assert_eq!(&hasher.finalize().unwrap()[..], &expected.finalize()[..]);
+
+    // Longer than the internal buffer, in one write.
+    let long = s.repeat(100);
+    let mut hasher = WeirdEncodingHasher::new();
+    hasher.write_all(long.as_bytes()).unwrap();
+    let weird: Vec<u8> = to_weird_encoding(&long).collect();
+    assert_eq!(hasher.len(), weird.len());
+    assert_eq!(&hasher.finalize().unwrap()[..], &Sha256::digest(&weird)[..]);
+}
+
+#[test]
+fn test_weird_encoding_hasher_invalid() {
+    use std::io::Write;
+
+    // Writes stop before the first invalid byte.
+    let mut hasher = WeirdEncodingHasher::new();
+    assert_eq!(hasher.write(b"ab\xFFc").unwrap(), 2);
+    assert!(hasher.write(b"\xFFc").is_err());
+    assert_eq!(hasher.len(), 2);
+    assert!(hasher.write_all(b"ab\xFF").is_err());
+    assert_eq!(hasher.len(), 4);
+
+    // A rejected byte is not consumed, the pending sequence can still be completed.
+    let mut hasher = WeirdEncodingHasher::new();
+    assert_eq!(hasher.write(b"\xC3a").unwrap(), 1);
+    assert!(hasher.write(b"a").is_err());
+    assert_eq!(hasher.write(b"\xA4").unwrap(), 1);
+    assert_eq!(hasher.len(), 1);
+    assert_eq!(
+        &hasher.finalize().unwrap()[..],
+        &Sha256::digest(&[0xE4])[..]
+    );
+
+    // Surrogates, overlong encodings and code points above U+10FFFF.
+    for invalid in &[
+        &b"\xED\xA0\x80"[..],
+        b"\xC0\x80",
+        b"\xE0\x80\x80",
+        b"\xF4\x90\x80\x80",
+    ] {
+        assert!(WeirdEncodingHasher::new().write_all(invalid).is_err());
+    }
+
+    // Incomplete sequences can not be finalized.
+    let mut hasher = WeirdEncodingHasher::new();
+    hasher.write_all(b"a\xF0\x9F\x98").unwrap();
+    assert!(hasher.finalize().is_err());
+}
+
+#[test]
+fn test_message_hash() {
+    // The expected digest was computed independently of this crate, by hashing the low bytes
+    // of the utf16 code units of `msg` with python's hashlib.
+    let msg = "{\n  \"type\": \"post\",\n  \"text\": \"h\u{e4}llo \u{20ac}\u{1f600}\"\n}";
+    assert_eq!(
+        message_hash(msg),
+        [
+            0x7a, 0xfa, 0xa1, 0x5b, 0x72, 0x46, 0x2b, 0x3d, 0xad, 0xb3, 0xfc, 0xdd, 0x81, 0x25,
+            0x57, 0x7f, 0xd2, 0x4f, 0x8d, 0x68, 0x93, 0x4f, 0x90, 0x7b, 0x4e, 0x90, 0xfd, 0xdf,
+            0xd4, 0x95, 0xa2, 0x2a,
+        ]
+    );
+
+    // Inputs longer than the internal buffer.
+    let long = "\u{1f600}x".repeat(300);
+    let weird: Vec<u8> = to_weird_encoding(&long).collect();
+    assert_eq!(&message_hash(&long)[..], &Sha256::digest(&weird)[..]);
+}
+
+#[test]
+fn test_hash_and_length() {
+    use super::legacy_length;
+
+    let long = "\u{1f600}x".repeat(300);
+    for s in &["", "a", "\u{e4}\u{20ac}", long.as_str()] {
+        let weird: Vec<u8> = to_weird_encoding(s).collect();
+        let (digest, len) = hash_and_length(s);
+
+        assert_eq!(&digest[..], &Sha256::digest(&weird)[..]);
+        assert_eq!(len, weird.len());
+        assert_eq!(len, legacy_length(s));
+    }
+}
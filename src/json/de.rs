@@ -1,14 +1,23 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::marker::PhantomData;
+use std::ops::Range;
 use std::slice::SliceIndex;
+use std::str::FromStr;
 use std::{error, fmt};
 
 use base64;
 use encode_unicode::{error::InvalidUtf16Tuple, Utf16Char, Utf8Char};
+use ryu_ecmascript;
+use serde::de::value::BorrowedStrDeserializer;
 use serde::de::{
     self, Deserialize, DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess,
     IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor,
 };
 use strtod2::strtod;
 
+use super::super::value::{RidiculousStringMap, Value};
 use super::super::LegacyF64;
 
 /// Error code and byte offset describing a deserialization failure
@@ -23,6 +32,7 @@ pub struct DecodeJsonError {
 
 /// Everything that can go wrong during deserialization.
 #[derive(PartialEq, Eq, Debug, Clone)]
+#[non_exhaustive]
 pub enum ErrorCode {
     /// Expected more data but the input ended.
     UnexpectedEndOfInput,
@@ -88,13 +98,136 @@ pub enum ErrorCode {
     ExpectedObject,
     /// Expected an enum, found something else.
     ExpectedEnum,
+    /// An object contained the same key twice.
+    DuplicateKey,
+    /// The input contained more array elements and object entries in total than allowed.
+    TooManyElements,
+    /// The input nested arrays and objects more deeply than allowed.
+    DepthLimitExceeded,
+    /// A number was not written in the form used by the
+    /// [signing encoding](https://spec.scuttlebutt.nz/feed/datamodel.html#signing-encoding-floats).
+    NonCanonicalNumber,
+    /// A string contained a unicode noncharacter while those were being rejected.
+    Noncharacter,
     /// Custom, stringly-typed error.
     Message(String),
 }
 
+/// A coarse classification of decoding errors, for callers that need to react to errors
+/// without inspecting every `ErrorCode`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum ErrorCategory {
+    /// The input is not valid (or not acceptable under the chosen options), and never will be.
+    Malformed,
+    /// The input exceeded a configured resource limit, decoding with higher limits might work.
+    ResourceLimit,
+    /// The input ended too early, decoding might work once more input is available.
+    UnexpectedEof,
+    /// Reading the input failed.
+    Io,
+}
+
+impl DecodeJsonError {
+    /// Returns `true` if the input was a valid prefix of some json value, i.e. decoding might
+    /// succeed if more input became available.
+    pub fn is_recoverable(&self) -> bool {
+        self.code == ErrorCode::UnexpectedEndOfInput
+    }
+
+    /// Classifies this error.
+    ///
+    /// ```
+    /// use ssb_legacy_msg_data::json::{from_slice, ErrorCategory};
+    /// use ssb_legacy_msg_data::value::Value;
+    ///
+    /// let err = from_slice::<Value>(b"[1, ").unwrap_err();
+    /// assert_eq!(err.category(), ErrorCategory::UnexpectedEof);
+    ///
+    /// let err = from_slice::<Value>(b"[1, }").unwrap_err();
+    /// assert_eq!(err.category(), ErrorCategory::Malformed);
+    /// ```
+    pub fn category(&self) -> ErrorCategory {
+        match self.code {
+            ErrorCode::UnexpectedEndOfInput => ErrorCategory::UnexpectedEof,
+            ErrorCode::TooManyElements | ErrorCode::DepthLimitExceeded => {
+                ErrorCategory::ResourceLimit
+            }
+            _ => ErrorCategory::Malformed,
+        }
+    }
+
+    /// Computes the 1-based line and column of this error within the `input` it was produced
+    /// from. `\n`, `\r\n` and a lone `\r` each count as a single line break, columns count
+    /// characters rather than bytes.
+    ///
+    /// ```
+    /// use ssb_legacy_msg_data::json::from_slice;
+    /// use ssb_legacy_msg_data::value::Value;
+    ///
+    /// let input = b"{\r\n  \"a\": [1,\r\n    tru]\r\n}";
+    /// let err = from_slice::<Value>(input).unwrap_err();
+    /// assert_eq!(err.line_column(input), (3, 5));
+    /// ```
+    pub fn line_column(&self, input: &[u8]) -> (usize, usize) {
+        let end = std::cmp::min(self.position, input.len());
+        let mut line = 1;
+        let mut column = 1;
+
+        for (i, &byte) in input[..end].iter().enumerate() {
+            match byte {
+                b'\n' => {
+                    line += 1;
+                    column = 1;
+                }
+                b'\r' if input.get(i + 1) != Some(&b'\n') => {
+                    line += 1;
+                    column = 1;
+                }
+                // utf8 continuation bytes don't start a new character
+                0x80..=0xBF => {}
+                _ => column += 1,
+            }
+        }
+
+        (line, column)
+    }
+
+    /// Returns an object that displays this error with the line and column (see
+    /// [`line_column`](struct.DecodeJsonError.html#method.line_column)) at which it occurred
+    /// in the `input` it was produced from.
+    ///
+    /// ```
+    /// use ssb_legacy_msg_data::json::from_slice;
+    /// use ssb_legacy_msg_data::value::Value;
+    ///
+    /// let input = b"[\n  1,\n  }";
+    /// let err = from_slice::<Value>(input).unwrap_err();
+    /// assert_eq!(err.display_in(input).to_string(), "Syntax at line 3, column 3");
+    /// ```
+    pub fn display_in<'a>(&'a self, input: &'a [u8]) -> DisplayInInput<'a> {
+        DisplayInInput { err: self, input }
+    }
+}
+
+/// Displays a `DecodeJsonError` with line and column information, see
+/// [`DecodeJsonError::display_in`](struct.DecodeJsonError.html#method.display_in).
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayInInput<'a> {
+    err: &'a DecodeJsonError,
+    input: &'a [u8],
+}
+
+impl<'a> fmt::Display for DisplayInInput<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> std::result::Result<(), fmt::Error> {
+        let (line, column) = self.err.line_column(self.input);
+        write!(f, "{:?} at line {}, column {}", self.err.code, line, column)
+    }
+}
+
 impl fmt::Display for DecodeJsonError {
     fn fmt(&self, f: &mut fmt::Formatter) -> std::result::Result<(), fmt::Error> {
-        fmt::Debug::fmt(&self.code, f)
+        write!(f, "{:?} at byte {}", self.code, self.position)
     }
 }
 
@@ -104,17 +237,91 @@ impl de::Error for DecodeJsonError {
     fn custom<T: fmt::Display>(msg: T) -> Self {
         DecodeJsonError {
             code: ErrorCode::Message(msg.to_string()),
-            position: 0, // TODO
+            // The actual position is filled in by `JsonDeserializer::deserialize_located`.
+            position: 0,
         }
     }
 }
 
+/// Everything that can go wrong when deserializing from an `io::Read`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ReadJsonError {
+    /// An IO error occured on the underlying reader.
+    Io(io::Error),
+    /// The input was invalid. The position is relative to the start of the reader.
+    Decode(DecodeJsonError),
+}
+
+impl fmt::Display for ReadJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> std::result::Result<(), fmt::Error> {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl error::Error for ReadJsonError {}
+
+impl ReadJsonError {
+    /// Classifies this error.
+    pub fn category(&self) -> ErrorCategory {
+        match *self {
+            ReadJsonError::Io(_) => ErrorCategory::Io,
+            ReadJsonError::Decode(ref e) => e.category(),
+        }
+    }
+}
+
+impl From<io::Error> for ReadJsonError {
+    fn from(e: io::Error) -> Self {
+        ReadJsonError::Io(e)
+    }
+}
+
+impl From<DecodeJsonError> for ReadJsonError {
+    fn from(e: DecodeJsonError) -> Self {
+        ReadJsonError::Decode(e)
+    }
+}
+
+/// Statistics about the shape of some decoded input.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct DecodeStats {
+    /// The deepest nesting of arrays and objects encountered. A scalar at top level has depth
+    /// zero, the elements of a top-level array have depth one, etc.
+    pub max_depth: usize,
+    /// The number of objects decoded.
+    pub objects: usize,
+    /// The number of arrays decoded.
+    pub arrays: usize,
+    /// The number of strings decoded, including object keys.
+    pub strings: usize,
+    /// The number of bytes consumed from the input.
+    pub bytes: usize,
+}
+
+/// The maximum nesting depth of arrays and objects that a `JsonDeserializer` accepts unless
+/// configured otherwise.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
 /// A structure that deserializes json encoded legacy message values.
 ///
 /// https://www.ecma-international.org/publications/files/ECMA-ST/ECMA-404.pdf
 pub struct JsonDeserializer<'de> {
     input: &'de [u8],
     position: usize,
+    // The number of array elements and object entries encountered so far.
+    elements: usize,
+    max_elements: usize,
+    // The number of currently open arrays and objects.
+    depth: usize,
+    max_depth: usize,
+    stats: DecodeStats,
+    // Whether to reject numbers not in the form of the signing encoding.
+    canonical_numbers: bool,
+    // Whether to accept some common deviations from json.
+    lenient: bool,
+    // Whether to reject strings containing unicode noncharacters.
+    reject_noncharacters: bool,
 }
 
 impl<'de> JsonDeserializer<'de> {
@@ -144,6 +351,14 @@ impl<'de> JsonDeserializer<'de> {
         self.position
     }
 
+    /// Statistics about the input that has been deserialized so far.
+    pub fn stats(&self) -> DecodeStats {
+        DecodeStats {
+            bytes: self.position(),
+            ..self.stats
+        }
+    }
+
     fn fail<T>(&self, code: ErrorCode) -> Result<T, DecodeJsonError> {
         Err(DecodeJsonError {
             code,
@@ -157,33 +372,244 @@ impl<'de> JsonDeserializer<'de> {
 }
 
 /// Try to parse data from the input. Validates that there are no trailing non-whitespace bytes.
-pub fn from_slice<T>(input: &[u8]) -> Result<T, DecodeJsonError>
+///
+/// Strings that contain no escape sequences can be borrowed from the input.
+///
+/// ```
+/// use ssb_legacy_msg_data::json::from_slice;
+///
+/// let input = br#"["abc", "def"]"#;
+/// let strs: Vec<&str> = from_slice(input).unwrap();
+/// assert_eq!(strs, vec!["abc", "def"]);
+/// ```
+pub fn from_slice<'de, T>(input: &'de [u8]) -> Result<T, DecodeJsonError>
 where
-    T: DeserializeOwned,
+    T: Deserialize<'de>,
 {
     let mut de = JsonDeserializer::from_slice(input);
-    match Deserialize::deserialize(&mut de) {
+    match de.deserialize_located(PhantomData) {
         Ok(t) => de.end().map(|_| t),
         Err(e) => Err(e),
     }
 }
 
+/// Try to parse data from the input, accepting some common deviations from json: a leading
+/// utf8 byte order mark, trailing commas and comments. See
+/// [`JsonDeserializer::with_skip_bom`](struct.JsonDeserializer.html#method.with_skip_bom) and
+/// [`JsonDeserializer::with_lenient`](struct.JsonDeserializer.html#method.with_lenient).
+/// Validates that there are no trailing non-whitespace bytes.
+///
+/// Input that needs any of these deviations is never a message in the signing encoding, so use
+/// [`from_slice`](fn.from_slice.html) for verifying messages.
+///
+/// ```
+/// use ssb_legacy_msg_data::json::{from_slice, from_slice_lenient};
+/// use ssb_legacy_msg_data::value::Value;
+///
+/// let input = b"\xEF\xBB\xBF[1, 2, // two\n]";
+/// assert!(from_slice::<Value>(input).is_err());
+/// assert_eq!(from_slice_lenient::<Vec<u8>>(input).unwrap(), vec![1, 2]);
+/// ```
+pub fn from_slice_lenient<'de, T>(input: &'de [u8]) -> Result<T, DecodeJsonError>
+where
+    T: Deserialize<'de>,
+{
+    let mut de = JsonDeserializer::from_slice(input)
+        .with_lenient(true)
+        .with_skip_bom(true);
+    match de.deserialize_located(PhantomData) {
+        Ok(t) => de.end().map(|_| t),
+        Err(e) => Err(e),
+    }
+}
+
+/// Try to parse data from the input, also returning statistics about it. Validates that there
+/// are no trailing non-whitespace bytes.
+///
+/// ```
+/// use ssb_legacy_msg_data::json::from_slice_with_stats;
+/// use ssb_legacy_msg_data::value::Value;
+///
+/// let (_, stats) = from_slice_with_stats::<Value>(b"{\"a\": [1, \"b\"]}").unwrap();
+/// assert_eq!(stats.max_depth, 2);
+/// assert_eq!(stats.strings, 2);
+/// ```
+pub fn from_slice_with_stats<T>(input: &[u8]) -> Result<(T, DecodeStats), DecodeJsonError>
+where
+    T: DeserializeOwned,
+{
+    let mut de = JsonDeserializer::from_slice(input);
+    match de.deserialize_located(PhantomData) {
+        Ok(t) => de.end().map(|_| (t, de.stats())),
+        Err(e) => Err(e),
+    }
+}
+
+/// Try to parse a `Value` from the input, reporting as many errors as possible instead of
+/// stopping at the first one.
+///
+/// After an error, decoding resumes at the next `,`, `]` or `}` on the same nesting level.
+/// This is a best-effort process intended for diagnostics: later errors may be consequences of
+/// earlier ones.
+///
+/// ```
+/// use ssb_legacy_msg_data::json::{from_slice_recovering, ErrorCode};
+///
+/// let errs = from_slice_recovering(br#"[1, tru, {"a" 2, "b": 3}, -0]"#).unwrap_err();
+/// let codes: Vec<_> = errs.into_iter().map(|e| e.code).collect();
+/// assert_eq!(
+///     codes,
+///     vec![ErrorCode::Syntax, ErrorCode::Colon, ErrorCode::InvalidNumber]
+/// );
+/// ```
+pub fn from_slice_recovering(input: &[u8]) -> Result<Value, Vec<DecodeJsonError>> {
+    let mut de = JsonDeserializer::from_slice(input);
+    let mut errors = vec![];
+
+    let value = de.recover_value(&mut errors);
+    if let Err(e) = de.end() {
+        errors.push(e);
+    }
+
+    match value {
+        Some(value) if errors.is_empty() => Ok(value),
+        _ => Err(errors),
+    }
+}
+
+/// Maps [json pointers](https://tools.ietf.org/html/rfc6901) to the byte ranges that the
+/// values they refer to occupy in some input, see `from_slice_spanned`.
+pub type SpanMap = BTreeMap<String, Range<usize>>;
+
+/// Try to parse a `Value` from the input, also returning the byte range of every node of the
+/// value. Validates that there are no trailing non-whitespace bytes.
+///
+/// The ranges of arrays and objects include their brackets and braces, the ranges of strings
+/// include their quotes. Leading and trailing whitespace is never included.
+///
+/// ```
+/// use ssb_legacy_msg_data::json::from_slice_spanned;
+///
+/// let input = br#"{"a": [1, "b"]}"#;
+/// let (_, spans) = from_slice_spanned(input).unwrap();
+/// assert_eq!(spans[""], 0..15);
+/// assert_eq!(&input[spans["/a"].clone()], br#"[1, "b"]"#);
+/// assert_eq!(&input[spans["/a/1"].clone()], br#""b""#);
+/// ```
+pub fn from_slice_spanned(input: &[u8]) -> Result<(Value, SpanMap), DecodeJsonError> {
+    let mut de = JsonDeserializer::from_slice(input);
+    let mut spans = SpanMap::new();
+
+    let value = de.spanned_value(&mut String::new(), &mut spans)?;
+    de.end().map(|_| (value, spans))
+}
+
+/// Try to parse data from a reader. Validates that there are no trailing non-whitespace bytes.
+///
+/// See [`BufferedReaderDeserializer`](struct.BufferedReaderDeserializer.html) for how the
+/// input is buffered. Since this reads until the end of the reader to check for trailing bytes,
+/// limit the reader to a frame via `io::Read::take` to decode length-prefixed frames, or use a
+/// `BufferedReaderDeserializer` directly to leave the input after the value unread.
+///
+/// ```
+/// use std::io::Read;
+/// use ssb_legacy_msg_data::json::from_reader;
+/// use ssb_legacy_msg_data::value::Value;
+///
+/// let val = from_reader::<Value, _>(&br#"{"a": [1, 2]}"#[..]).unwrap();
+/// assert_eq!(val.pointer("/a/1").and_then(Value::as_f64), Some(2.0));
+///
+/// // Two frames of four bytes each.
+/// let mut frames = &b"true[ 1]"[..];
+/// assert!(from_reader::<bool, _>((&mut frames).take(4)).unwrap());
+/// assert_eq!(from_reader::<Vec<u8>, _>((&mut frames).take(4)).unwrap(), vec![1]);
+/// ```
+pub fn from_reader<T, R>(reader: R) -> Result<T, ReadJsonError>
+where
+    T: DeserializeOwned,
+    R: io::BufRead,
+{
+    let mut de = BufferedReaderDeserializer::new(reader);
+    let t = de.next_value()?;
+    de.end().map(|_| t)
+}
+
+/// Parse newline-delimited json: every line of the input holds one value. Lines that contain
+/// nothing but whitespace are skipped.
+///
+/// ```
+/// use ssb_legacy_msg_data::json::read_ndjson;
+///
+/// let values: Vec<_> = read_ndjson(&b"null\n\n[true]\n"[..]).collect();
+/// assert_eq!(values.len(), 2);
+/// assert!(values[1].as_ref().unwrap().is_array());
+/// ```
+pub fn read_ndjson<R: io::BufRead>(reader: R) -> NdjsonIter<R> {
+    NdjsonIter {
+        reader,
+        line: Vec::new(),
+        offset: 0,
+    }
+}
+
+/// An iterator over the values of some newline-delimited json, see
+/// [`read_ndjson`](fn.read_ndjson.html). Error positions are relative to the start of the
+/// input.
+pub struct NdjsonIter<R> {
+    reader: R,
+    line: Vec<u8>,
+    // The number of bytes read so far.
+    offset: usize,
+}
+
+impl<R: io::BufRead> Iterator for NdjsonIter<R> {
+    type Item = Result<Value, ReadJsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.offset;
+            self.line.clear();
+            match self.reader.read_until(0x0A, &mut self.line) {
+                Ok(0) => return None,
+                Ok(n) => self.offset += n,
+                Err(e) => return Some(Err(ReadJsonError::Io(e))),
+            }
+
+            if self.line.iter().all(|byte| is_ws(*byte)) {
+                continue;
+            }
+
+            return Some(from_slice(&self.line).map_err(|e| {
+                ReadJsonError::Decode(DecodeJsonError {
+                    code: e.code,
+                    position: start + e.position,
+                })
+            }));
+        }
+    }
+}
+
 /// Try to parse data from the input, returning the remaining input when done.
 pub fn from_slice_partial<T>(input: &[u8]) -> Result<(T, &[u8]), DecodeJsonError>
 where
     T: DeserializeOwned,
 {
     let mut de = JsonDeserializer::from_slice(input);
-    match Deserialize::deserialize(&mut de) {
+    match de.deserialize_located(PhantomData) {
         Ok(t) => Ok((t, de.rest())),
         Err(e) => Err(e),
     }
 }
 
-fn is_ws(byte: u8) -> bool {
+pub(super) fn is_ws(byte: u8) -> bool {
     byte == 0x09 || byte == 0x0A || byte == 0x0D || byte == 0x20
 }
 
+// Whether the byte may occur in a number, `true`, `false` or `null`.
+fn is_scalar_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == 0x2B || byte == 0x2D || byte == 0x2E
+}
+
 fn is_digit(byte: u8) -> bool {
     byte.is_ascii_digit()
 }
@@ -192,10 +618,206 @@ fn is_hex_digit(byte: u8) -> bool {
     byte.is_ascii_hexdigit()
 }
 
+// The utf8 encoding of U+FEFF.
+static UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
 impl<'de> JsonDeserializer<'de> {
     /// Creates a `Deserializer` from a `&[u8]`.
     pub fn from_slice(input: &'de [u8]) -> Self {
-        JsonDeserializer { input, position: 0 }
+        JsonDeserializer {
+            input,
+            position: 0,
+            elements: 0,
+            max_elements: usize::MAX,
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            stats: DecodeStats::default(),
+            canonical_numbers: false,
+            lenient: false,
+            reject_noncharacters: false,
+        }
+    }
+
+    /// If `lenient` is `true`, accepts trailing commas in arrays and objects, as well as `//`
+    /// line comments and `/* */` block comments wherever whitespace is allowed.
+    ///
+    /// This is intended for importing hand-written data. Input that uses these deviations
+    /// from json is never in the signing encoding, so it can not carry a valid signature.
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// If `skip` is `true` and the input begins with a utf8 byte order mark (`EF BB BF`), the
+    /// byte order mark and any whitespace following it are skipped. Otherwise, it is rejected as
+    /// a syntax error. Error positions are still relative to the start of the input, including
+    /// the byte order mark.
+    ///
+    /// A byte order mark is not part of any message in the signing encoding, and the hash of a
+    /// message is computed over all of its bytes. So never skip it when verifying messages.
+    ///
+    /// ```
+    /// extern crate serde;
+    /// extern crate ssb_legacy_msg_data;
+    ///
+    /// use serde::Deserialize;
+    /// use ssb_legacy_msg_data::json::JsonDeserializer;
+    ///
+    /// let mut de = JsonDeserializer::from_slice(b"\xEF\xBB\xBFtrue").with_skip_bom(true);
+    /// assert_eq!(bool::deserialize(&mut de), Ok(true));
+    ///
+    /// let mut de = JsonDeserializer::from_slice(b"\xEF\xBB\xBFtrue");
+    /// assert!(bool::deserialize(&mut de).is_err());
+    /// ```
+    pub fn with_skip_bom(mut self, skip: bool) -> Self {
+        if skip && self.position() == 0 && self.input.starts_with(UTF8_BOM) {
+            self.advance(UTF8_BOM.len());
+            self.skip_ws();
+        }
+        self
+    }
+
+    /// If `canonical` is `true`, every number must be written exactly as the
+    /// [signing encoding](https://spec.scuttlebutt.nz/feed/datamodel.html#signing-encoding-floats)
+    /// would write it, otherwise decoding fails with an `ErrorCode::NonCanonicalNumber`.
+    ///
+    /// ```
+    /// extern crate serde;
+    /// extern crate ssb_legacy_msg_data;
+    ///
+    /// use serde::Deserialize;
+    /// use ssb_legacy_msg_data::json::{ErrorCode, JsonDeserializer};
+    ///
+    /// let mut de = JsonDeserializer::from_slice(b"0.5").with_canonical_numbers(true);
+    /// assert_eq!(f64::deserialize(&mut de), Ok(0.5));
+    ///
+    /// let mut de = JsonDeserializer::from_slice(b"1.0").with_canonical_numbers(true);
+    /// assert_eq!(f64::deserialize(&mut de).unwrap_err().code, ErrorCode::NonCanonicalNumber);
+    /// ```
+    pub fn with_canonical_numbers(mut self, canonical: bool) -> Self {
+        self.canonical_numbers = canonical;
+        self
+    }
+
+    /// If `reject` is `true`, strings (including object keys) may not contain unicode
+    /// noncharacters (U+FDD0 to U+FDEF, and the last two code points of every plane), whether
+    /// escaped or not. Decoding such a string fails with an `ErrorCode::Noncharacter`.
+    ///
+    /// Noncharacters are valid ssb data, this is for consumers that can not handle them.
+    ///
+    /// ```
+    /// extern crate serde;
+    /// extern crate ssb_legacy_msg_data;
+    ///
+    /// use serde::Deserialize;
+    /// use ssb_legacy_msg_data::json::{ErrorCode, JsonDeserializer};
+    ///
+    /// let mut de = JsonDeserializer::from_slice(br#""\uFFFE""#);
+    /// assert_eq!(String::deserialize(&mut de), Ok("\u{FFFE}".to_string()));
+    ///
+    /// let mut de = JsonDeserializer::from_slice(br#""\uFFFE""#).with_reject_noncharacters(true);
+    /// assert_eq!(String::deserialize(&mut de).unwrap_err().code, ErrorCode::Noncharacter);
+    /// ```
+    pub fn with_reject_noncharacters(mut self, reject: bool) -> Self {
+        self.reject_noncharacters = reject;
+        self
+    }
+
+    /// Limits the nesting depth of arrays and objects, the default is
+    /// [`DEFAULT_MAX_DEPTH`](constant.DEFAULT_MAX_DEPTH.html). Exceeding the limit results in an
+    /// `ErrorCode::DepthLimitExceeded` at the opening bracket or brace.
+    ///
+    /// Decoding recurses once per nesting level, so this limit protects against malicious input
+    /// overflowing the stack.
+    ///
+    /// ```
+    /// extern crate serde;
+    /// extern crate ssb_legacy_msg_data;
+    ///
+    /// use serde::Deserialize;
+    /// use ssb_legacy_msg_data::json::{ErrorCode, JsonDeserializer};
+    /// use ssb_legacy_msg_data::value::Value;
+    ///
+    /// let mut de = JsonDeserializer::from_slice(b"[[1], 2]").with_max_depth(2);
+    /// assert!(Value::deserialize(&mut de).is_ok());
+    ///
+    /// let mut de = JsonDeserializer::from_slice(b"[[[1]], 2]").with_max_depth(2);
+    /// let err = Value::deserialize(&mut de).unwrap_err();
+    /// assert_eq!((err.code, err.position), (ErrorCode::DepthLimitExceeded, 2));
+    /// ```
+    pub fn with_max_depth(mut self, max: usize) -> Self {
+        self.max_depth = max;
+        self
+    }
+
+    /// Limits the total number of array elements and object entries (across all nesting
+    /// levels) that may be decoded. Exceeding the limit results in an
+    /// `ErrorCode::TooManyElements`.
+    ///
+    /// ```
+    /// extern crate serde;
+    /// extern crate ssb_legacy_msg_data;
+    ///
+    /// use serde::Deserialize;
+    /// use ssb_legacy_msg_data::json::{ErrorCode, JsonDeserializer};
+    /// use ssb_legacy_msg_data::value::Value;
+    ///
+    /// let input = b"[[], [], {\"a\": []}]";
+    /// let mut de = JsonDeserializer::from_slice(input).with_max_total_elements(3);
+    /// assert_eq!(Value::deserialize(&mut de).unwrap_err().code, ErrorCode::TooManyElements);
+    /// ```
+    pub fn with_max_total_elements(mut self, max: usize) -> Self {
+        self.max_elements = max;
+        self
+    }
+
+    // Deserializes a value. Errors created through `de::Error::custom` (e.g. by a visitor
+    // rejecting the value) can not know where they occurred, they are attributed to the start
+    // of the value.
+    fn deserialize_located<S>(&mut self, seed: S) -> Result<S::Value, DecodeJsonError>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        self.peek_ws()?;
+        let start = self.position();
+
+        seed.deserialize(&mut *self).map_err(|mut e| {
+            if let ErrorCode::Message(_) = e.code {
+                if e.position == 0 {
+                    e.position = start;
+                }
+            }
+            e
+        })
+    }
+
+    // Registers that an array or object has been opened (its opening bracket has just been
+    // consumed), failing if that exceeds the maximum depth.
+    fn enter(&mut self) -> Result<(), DecodeJsonError> {
+        if self.depth >= self.max_depth {
+            return self.fail_at_position(ErrorCode::DepthLimitExceeded, self.position() - 1);
+        }
+
+        self.depth += 1;
+        if self.depth > self.stats.max_depth {
+            self.stats.max_depth = self.depth;
+        }
+        Ok(())
+    }
+
+    // Registers that an array or object has been closed.
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    // Registers a new array element or object entry, failing if there are too many.
+    fn count_element(&mut self) -> Result<(), DecodeJsonError> {
+        if self.elements >= self.max_elements {
+            self.fail(ErrorCode::TooManyElements)
+        } else {
+            self.elements += 1;
+            Ok(())
+        }
     }
 
     // Advance the input slice by some number of bytes.
@@ -264,7 +886,27 @@ impl<'de> JsonDeserializer<'de> {
     }
 
     fn skip_ws(&mut self) {
-        self.skip(is_ws)
+        self.skip(is_ws);
+
+        if self.lenient {
+            loop {
+                if self.rest().starts_with(b"//") {
+                    match self.rest().iter().position(|byte| *byte == 0x0A) {
+                        Some(offset) => self.advance(offset + 1),
+                        None => self.advance(self.rest().len()),
+                    }
+                } else if self.rest().starts_with(b"/*") {
+                    match self.rest()[2..].windows(2).position(|w| w == b"*/") {
+                        Some(offset) => self.advance(offset + 4),
+                        None => self.advance(self.rest().len()),
+                    }
+                } else {
+                    return;
+                }
+
+                self.skip(is_ws);
+            }
+        }
     }
 
     // Consumes as much whitespace as possible, then peeks at the next non-whitespace byte.
@@ -374,16 +1016,24 @@ impl<'de> JsonDeserializer<'de> {
             strtod(unsafe { std::str::from_utf8_unchecked(self.slice(start..self.position())) })
                 .unwrap(); // We already checked that the input is a valid number
 
-        if LegacyF64::is_valid(f) {
-            Ok(f)
-        } else {
-            self.fail_at_position(ErrorCode::InvalidNumber, start)
+        if !LegacyF64::is_valid(f) {
+            return self.fail_at_position(ErrorCode::InvalidNumber, start);
         }
+
+        if self.canonical_numbers {
+            let mut buffer = ryu_ecmascript::Buffer::new();
+            if buffer.format::<f64>(f).as_bytes() != self.slice(start..self.position()) {
+                return self.fail_at_position(ErrorCode::NonCanonicalNumber, start);
+            }
+        }
+
+        Ok(f)
     }
 
     // Return a slice beginning and ending with 0x22 (`"`)
     fn parse_naive_string(&mut self) -> Result<&'de [u8], DecodeJsonError> {
         self.expect(0x22, ErrorCode::ExpectedString)?;
+        self.stats.strings += 1;
         let start = self.position();
 
         while self.next()? != 0x22 {
@@ -395,6 +1045,7 @@ impl<'de> JsonDeserializer<'de> {
 
     fn parse_string(&mut self) -> Result<String, DecodeJsonError> {
         self.expect(0x22, ErrorCode::ExpectedString)?;
+        self.stats.strings += 1;
 
         let mut decoded = String::new();
 
@@ -426,9 +1077,10 @@ impl<'de> JsonDeserializer<'de> {
                         0x75 => {
                             let cp = self.parse_unicode_escape()?;
 
-                            match code_unit_type(cp) {
-                                CodeUnitType::Valid => decoded
-                                    .push(unsafe { std::char::from_u32_unchecked(cp as u32) }),
+                            let c = match code_unit_type(cp) {
+                                CodeUnitType::Valid => unsafe {
+                                    std::char::from_u32_unchecked(cp as u32)
+                                },
 
                                 CodeUnitType::LeadingSurrogate => {
                                     // the unicode escape was for a leading surrogate, which
@@ -439,7 +1091,7 @@ impl<'de> JsonDeserializer<'de> {
                                     let cp2 = self.parse_unicode_escape()?;
 
                                     match Utf16Char::from_tuple((cp, Some(cp2))) {
-                                        Ok(c) => decoded.push(c.into()),
+                                        Ok(c) => c.into(),
                                         Err(e) => {
                                             return self
                                                 .fail_at_position(ErrorCode::SurrogatePair(e), pos)
@@ -450,7 +1102,10 @@ impl<'de> JsonDeserializer<'de> {
                                 CodeUnitType::TrailingSurrogate => {
                                     return self.fail_at_position(ErrorCode::TrailingSurrogate, pos)
                                 }
-                            }
+                            };
+
+                            self.check_noncharacter(c, pos)?;
+                            decoded.push(c);
                         }
 
                         // Nothing else may follow an unescaped `\`
@@ -464,20 +1119,594 @@ impl<'de> JsonDeserializer<'de> {
                 // a regular utf8-encoded code point (unless it is malformed)
                 _ => match Utf8Char::from_slice_start(self.rest()) {
                     Err(_) => return self.fail(ErrorCode::InvalidUtf8String),
-                    Ok((_, len)) => unsafe {
-                        decoded.push_str(std::str::from_utf8_unchecked(&self.rest()[..len]));
+                    Ok((c, len)) => {
+                        self.check_noncharacter(c.into(), self.position())?;
+                        unsafe {
+                            decoded.push_str(std::str::from_utf8_unchecked(&self.rest()[..len]));
+                        }
                         self.advance(len);
-                    },
+                    }
                 },
             }
         }
     }
 
+    // Parses a string that contains no escape sequences, borrowing it from the input. Returns
+    // `None` without consuming anything if the string can not be borrowed (or is invalid), in
+    // which case `parse_string` must be used instead.
+    fn parse_borrowed_string(&mut self) -> Option<&'de str> {
+        let rest = self.rest();
+        if rest.first() != Some(&0x22) {
+            return None;
+        }
+
+        let len = rest[1..]
+            .iter()
+            .position(|&b| b == 0x22 || b == 0x5C || b <= 0x1F)?;
+        if rest[1 + len] != 0x22 {
+            return None;
+        }
+
+        let s = std::str::from_utf8(&rest[1..1 + len]).ok()?;
+        if self.reject_noncharacters && s.chars().any(is_noncharacter) {
+            return None;
+        }
+
+        self.stats.strings += 1;
+        self.advance(len + 2);
+        Some(s)
+    }
+
+    // Fails if noncharacters are rejected and `c` is one, reporting the given position.
+    fn check_noncharacter(&self, c: char, position: usize) -> Result<(), DecodeJsonError> {
+        if self.reject_noncharacters && is_noncharacter(c) {
+            self.fail_at_position(ErrorCode::Noncharacter, position)
+        } else {
+            Ok(())
+        }
+    }
+
     fn parse_null(&mut self) -> Result<(), DecodeJsonError> {
         self.expect_bytes(b"null", ErrorCode::ExpectedNull)
     }
 }
 
+// Whether the given char is a unicode noncharacter.
+fn is_noncharacter(c: char) -> bool {
+    let n = c as u32;
+    (0xFDD0..=0xFDEF).contains(&n) || n & 0xFFFE == 0xFFFE
+}
+
+impl FromStr for LegacyF64 {
+    type Err = DecodeJsonError;
+
+    /// Parses a json number into a `LegacyF64`, following the same grammar as the decoder.
+    ///
+    /// ```
+    /// use ssb_legacy_msg_data::LegacyF64;
+    ///
+    /// assert_eq!("1.5e3".parse::<LegacyF64>().map(f64::from), Ok(1500.0));
+    /// assert!("-0".parse::<LegacyF64>().is_err());
+    /// assert!("1e999".parse::<LegacyF64>().is_err());
+    /// assert!(" 1".parse::<LegacyF64>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut de = JsonDeserializer::from_slice(s.as_bytes());
+        let f = de.parse_number()?;
+
+        if de.rest().is_empty() {
+            Ok(LegacyF64(f))
+        } else {
+            de.fail(ErrorCode::TrailingCharacters)
+        }
+    }
+}
+
+// Decoding that recovers from errors, see `from_slice_recovering`.
+impl<'de> JsonDeserializer<'de> {
+    // Decodes a value, recording errors rather than returning them. Returns `None` if no value
+    // could be decoded, in which case the input has been skipped to the next `,`, `]` or `}`
+    // on the current nesting level.
+    fn recover_value(&mut self, errors: &mut Vec<DecodeJsonError>) -> Option<Value> {
+        match self.peek_ws() {
+            Err(e) => {
+                errors.push(e);
+                None
+            }
+            Ok(0x5B) => self.recover_array(errors),
+            Ok(0x7B) => self.recover_object(errors),
+            Ok(peeked) => match Value::deserialize(&mut *self) {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    errors.push(e);
+                    if peeked == 0x22 {
+                        self.skip_string_rest();
+                    }
+                    self.resync();
+                    None
+                }
+            },
+        }
+    }
+
+    fn recover_array(&mut self, errors: &mut Vec<DecodeJsonError>) -> Option<Value> {
+        self.advance(1); // `[`
+        if let Err(e) = self.enter() {
+            errors.push(e);
+            self.skip_collection_rest();
+            return None;
+        }
+        let mut v = Vec::new();
+        let mut first = true;
+
+        loop {
+            match self.peek_ws() {
+                Err(e) => {
+                    errors.push(e);
+                    break;
+                }
+                Ok(0x5D) => {
+                    self.advance(1);
+                    break;
+                }
+                Ok(0x7D) => {
+                    errors.push(self.error(ErrorCode::Syntax));
+                    self.advance(1);
+                    break;
+                }
+                Ok(0x2C) if !first => self.advance(1),
+                Ok(_) if !first => {
+                    errors.push(self.error(ErrorCode::Comma));
+                    self.resync();
+                    continue;
+                }
+                Ok(_) => first = false,
+            }
+
+            if let Some(inner) = self.recover_value(errors) {
+                v.push(inner);
+            }
+        }
+
+        self.leave();
+        Some(Value::Array(v))
+    }
+
+    fn recover_object(&mut self, errors: &mut Vec<DecodeJsonError>) -> Option<Value> {
+        self.advance(1); // `{`
+        if let Err(e) = self.enter() {
+            errors.push(e);
+            self.skip_collection_rest();
+            return None;
+        }
+        let mut m = RidiculousStringMap::with_capacity(0);
+        let mut first = true;
+
+        loop {
+            match self.peek_ws() {
+                Err(e) => {
+                    errors.push(e);
+                    break;
+                }
+                Ok(0x7D) => {
+                    self.advance(1);
+                    break;
+                }
+                Ok(0x5D) => {
+                    errors.push(self.error(ErrorCode::Syntax));
+                    self.advance(1);
+                    break;
+                }
+                Ok(0x2C) if !first => self.advance(1),
+                Ok(_) if !first => {
+                    errors.push(self.error(ErrorCode::Comma));
+                    self.resync();
+                    continue;
+                }
+                Ok(_) => first = false,
+            }
+
+            let key_pos = match self.peek_ws() {
+                Ok(_) => self.position(),
+                Err(e) => {
+                    errors.push(e);
+                    break;
+                }
+            };
+            let key = match self.parse_string() {
+                Ok(key) => key,
+                Err(e) => {
+                    errors.push(e);
+                    if self.input[key_pos] == 0x22 {
+                        self.skip_string_rest();
+                    }
+                    self.resync();
+                    continue;
+                }
+            };
+
+            if let Err(e) = self.expect_ws(0x3A, ErrorCode::Colon) {
+                errors.push(e);
+                self.resync();
+                continue;
+            }
+
+            if let Some(val) = self.recover_value(errors) {
+                if m.insert(key, val).is_some() {
+                    errors.push(DecodeJsonError {
+                        code: ErrorCode::DuplicateKey,
+                        position: key_pos,
+                    });
+                }
+            }
+        }
+
+        self.leave();
+        Some(Value::Object(m))
+    }
+
+    // Skips ahead to just after the `]` or `}` that closes the innermost open array or object.
+    fn skip_collection_rest(&mut self) {
+        loop {
+            self.resync();
+            match self.peek_or_end() {
+                Some(0x2C) => self.advance(1),
+                Some(_) => {
+                    self.advance(1);
+                    return;
+                }
+                None => return,
+            }
+        }
+    }
+
+    // Skips ahead to the next `,`, `]` or `}` that is not nested inside some array, object or
+    // string.
+    fn resync(&mut self) {
+        let mut depth = 0usize;
+
+        while let Some(byte) = self.peek_or_end() {
+            match byte {
+                0x22 => {
+                    self.advance(1);
+                    self.skip_string_rest();
+                    continue;
+                }
+                0x5B | 0x7B => depth += 1,
+                0x5D | 0x7D if depth == 0 => return,
+                0x5D | 0x7D => depth -= 1,
+                0x2C if depth == 0 => return,
+                _ => {}
+            }
+            self.advance(1);
+        }
+    }
+
+    // Skips ahead to just after the next unescaped `"`.
+    fn skip_string_rest(&mut self) {
+        while let Some(byte) = self.peek_or_end() {
+            self.advance(1);
+            match byte {
+                0x22 => return,
+                0x5C if self.peek_or_end().is_some() => self.advance(1),
+                _ => {}
+            }
+        }
+    }
+
+    fn error(&self, code: ErrorCode) -> DecodeJsonError {
+        DecodeJsonError {
+            code,
+            position: self.position(),
+        }
+    }
+}
+
+// Decoding that records spans, see `from_slice_spanned`.
+impl<'de> JsonDeserializer<'de> {
+    // Decodes a value, recording its span and the spans of its descendants. `pointer` refers to
+    // the value, it is restored before returning successfully.
+    fn spanned_value(
+        &mut self,
+        pointer: &mut String,
+        spans: &mut SpanMap,
+    ) -> Result<Value, DecodeJsonError> {
+        let peeked = self.peek_ws()?;
+        let start = self.position();
+
+        let value = match peeked {
+            0x5B => self.spanned_array(pointer, spans)?,
+            0x7B => self.spanned_object(pointer, spans)?,
+            _ => Value::deserialize(&mut *self)?,
+        };
+
+        spans.insert(pointer.clone(), start..self.position());
+        Ok(value)
+    }
+
+    fn spanned_array(
+        &mut self,
+        pointer: &mut String,
+        spans: &mut SpanMap,
+    ) -> Result<Value, DecodeJsonError> {
+        self.advance(1); // `[`
+        self.stats.arrays += 1;
+        self.enter()?;
+        let mut v = Vec::new();
+
+        while self.spanned_next(0x5D, v.is_empty())? {
+            let len = pointer.len();
+            pointer.push('/');
+            pointer.push_str(&v.len().to_string());
+            v.push(self.spanned_value(pointer, spans)?);
+            pointer.truncate(len);
+        }
+
+        self.advance(1); // `]`
+        self.leave();
+        Ok(Value::Array(v))
+    }
+
+    fn spanned_object(
+        &mut self,
+        pointer: &mut String,
+        spans: &mut SpanMap,
+    ) -> Result<Value, DecodeJsonError> {
+        self.advance(1); // `{`
+        self.stats.objects += 1;
+        self.enter()?;
+        let mut m = RidiculousStringMap::with_capacity(0);
+
+        while self.spanned_next(0x7D, m.is_empty())? {
+            let key_pos = self.position();
+            let key = self.parse_string()?;
+            if m.get(&key).is_some() {
+                return self.fail_at_position(ErrorCode::DuplicateKey, key_pos);
+            }
+            self.expect_ws(0x3A, ErrorCode::Colon)?;
+
+            let len = pointer.len();
+            pointer.push('/');
+            pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+            let val = self.spanned_value(pointer, spans)?;
+            pointer.truncate(len);
+
+            m.insert(key, val);
+        }
+
+        self.advance(1); // `}`
+        self.leave();
+        Ok(Value::Object(m))
+    }
+
+    // Moves to the next array element or object entry, returning `false` (without consuming
+    // it) if the collection ends at `close` instead.
+    fn spanned_next(&mut self, close: u8, first: bool) -> Result<bool, DecodeJsonError> {
+        if self.peek_ws()? == close {
+            return Ok(false);
+        }
+
+        // expect `,` before every item except the first
+        if !first {
+            self.expect_ws(0x2C, ErrorCode::Comma)?;
+        }
+
+        // in lenient mode, the last item may be followed by a `,`
+        if self.peek_ws()? == close && self.lenient {
+            return Ok(false);
+        }
+
+        self.count_element()?;
+        Ok(true)
+    }
+}
+
+/// Deserializes a sequence of whitespace-separated json values from an `io::BufRead`.
+///
+/// The input is scanned for the end of the next value as it arrives, and the bytes of the value
+/// are copied out of the reader's buffer. Once the end has been found, the value is decoded from
+/// the copied bytes. So every top-level value is held in memory in its entirety while it is
+/// being decoded: decoding a single large value needs as much memory as decoding it from a
+/// slice. What this saves is holding on to the whole input: values that have been decoded are
+/// dropped, so this works for unbounded streams of values and for sockets that do not signal the
+/// end of the input after a value.
+///
+/// Only the bytes up to the end of a value are consumed from the reader, everything after it
+/// stays available to other readers. To read from an `io::Read`, wrap it in an `io::BufReader`.
+///
+/// A top-level number is only known to be complete once it is followed by some other byte or
+/// the end of the input. Lenient mode is not supported.
+///
+/// ```
+/// use ssb_legacy_msg_data::json::BufferedReaderDeserializer;
+/// use ssb_legacy_msg_data::value::Value;
+///
+/// let mut de = BufferedReaderDeserializer::new(&b"[true] \"a\"\n{}"[..]);
+/// assert_eq!(de.next_value::<Value>().unwrap(), Value::Array(vec![Value::Bool(true)]));
+/// assert_eq!(de.next_value::<String>().unwrap(), "a");
+/// assert!(de.next_value::<Value>().unwrap().is_object());
+/// assert!(de.end().is_ok());
+/// ```
+pub struct BufferedReaderDeserializer<R> {
+    reader: R,
+    // The bytes of the next value that have been consumed from the reader so far.
+    buf: Vec<u8>,
+    scan: Scan,
+    // The number of bytes that were consumed from the reader before those in `buf`.
+    offset: usize,
+}
+
+impl<R: io::BufRead> BufferedReaderDeserializer<R> {
+    /// Creates a deserializer that reads from the given reader.
+    pub fn new(reader: R) -> Self {
+        BufferedReaderDeserializer {
+            reader,
+            buf: Vec::new(),
+            scan: Scan::default(),
+            offset: 0,
+        }
+    }
+
+    /// Decodes the next value, consuming the input up to the end of the value but no further.
+    pub fn next_value<T: DeserializeOwned>(&mut self) -> Result<T, ReadJsonError> {
+        self.frame()?;
+        let start = self.offset;
+        self.offset += self.buf.len();
+        self.scan = Scan::default();
+
+        let decoded = {
+            let mut de = JsonDeserializer::from_slice(&self.buf);
+            de.deserialize_located(PhantomData)
+                .and_then(|t| de.end().map(|_| t))
+        };
+        self.buf.clear();
+
+        decoded.map_err(|e| {
+            ReadJsonError::Decode(DecodeJsonError {
+                code: e.code,
+                position: start + e.position,
+            })
+        })
+    }
+
+    /// Check whether there are no non-whitespace bytes up until the end of the reader.
+    pub fn end(&mut self) -> Result<(), ReadJsonError> {
+        // `buf` is only non-empty if reading the previous value failed.
+        let pending = self.buf.len();
+        if let Some(i) = self.buf.iter().position(|byte| !is_ws(*byte)) {
+            return Err(ReadJsonError::Decode(DecodeJsonError {
+                code: ErrorCode::TrailingCharacters,
+                position: self.offset + i,
+            }));
+        }
+        self.offset += pending;
+        self.buf.clear();
+        self.scan = Scan::default();
+
+        loop {
+            let (ws, len) = {
+                let available = match self.reader.fill_buf() {
+                    Ok(available) => available,
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(ReadJsonError::Io(e)),
+                };
+                let ws = available.iter().take_while(|byte| is_ws(**byte)).count();
+                (ws, available.len())
+            };
+
+            self.reader.consume(ws);
+            self.offset += ws;
+            if len == 0 {
+                return Ok(());
+            } else if ws < len {
+                return Err(ReadJsonError::Decode(DecodeJsonError {
+                    code: ErrorCode::TrailingCharacters,
+                    position: self.offset,
+                }));
+            }
+        }
+    }
+
+    // Consumes input until `buf` contains the next value (or the reader ends).
+    fn frame(&mut self) -> Result<(), io::Error> {
+        loop {
+            let (consumed, complete) = {
+                let available = match self.reader.fill_buf() {
+                    Ok(available) => available,
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+                if available.is_empty() {
+                    return Ok(());
+                }
+
+                let len = self.buf.len();
+                let mut end = None;
+                for (i, byte) in available.iter().enumerate() {
+                    if let Some(e) = self.scan.step(*byte, len + i) {
+                        end = Some(e - len);
+                        break;
+                    }
+                }
+
+                let consumed = end.unwrap_or(available.len());
+                self.buf.extend_from_slice(&available[..consumed]);
+                (consumed, end.is_some())
+            };
+
+            self.reader.consume(consumed);
+            if complete {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// Tracks the nesting of a partially read json value, just enough to tell where it ends.
+// Invalid input may be misjudged; the deserializer then reports the error.
+#[derive(Default)]
+struct Scan {
+    // Whether a non-whitespace byte has been seen.
+    started: bool,
+    // The number of currently open arrays and objects.
+    depth: usize,
+    in_string: bool,
+    // Whether the previous byte was a backslash inside a string.
+    escaped: bool,
+}
+
+impl Scan {
+    // Processes the byte at index `i`, returning the index just past the value if the byte
+    // completes it.
+    fn step(&mut self, byte: u8, i: usize) -> Option<usize> {
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == 0x5C {
+                self.escaped = true;
+            } else if byte == 0x22 {
+                self.in_string = false;
+                if self.depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            return None;
+        }
+
+        if self.started && self.depth == 0 {
+            // Inside a top-level scalar such as a number or `true`, which ends at the first
+            // byte that can not belong to it.
+            return if is_scalar_byte(byte) { None } else { Some(i) };
+        }
+
+        match byte {
+            0x22 => {
+                self.started = true;
+                self.in_string = true;
+                None
+            }
+            0x5B | 0x7B => {
+                self.started = true;
+                self.depth += 1;
+                None
+            }
+            0x5D | 0x7D => {
+                self.depth = self.depth.saturating_sub(1);
+                if self.depth == 0 {
+                    Some(i + 1)
+                } else {
+                    None
+                }
+            }
+            _ if is_ws(byte) || self.depth > 0 => None,
+            _ => {
+                self.started = true;
+                None
+            }
+        }
+    }
+}
+
 // Every utf16 code unit (a `u16`) falls into one of these categories.
 enum CodeUnitType {
     // A valid code point in the BMP: either between 0x0000 and 0xD7FF (inclusive)
@@ -675,10 +1904,12 @@ impl<'a, 'de> Deserializer<'de> for &'a mut JsonDeserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        // We can't reference json strings directly since they contain escape sequences.
-        // For the conversion, we need to allocate an owned buffer, so always do owned
-        // deserialization.
-        self.deserialize_string(visitor)
+        // Strings without escape sequences can be referenced directly, all others need to be
+        // decoded into an owned buffer.
+        match self.parse_borrowed_string() {
+            Some(s) => visitor.visit_borrowed_str(s),
+            None => self.deserialize_string(visitor),
+        }
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -756,8 +1987,11 @@ impl<'a, 'de> Deserializer<'de> for &'a mut JsonDeserializer<'de> {
         V: Visitor<'de>,
     {
         self.expect(0x5B, ErrorCode::ExpectedArray)?;
+        self.stats.arrays += 1;
+        self.enter()?;
         let value = visitor.visit_seq(CollectionAccessor::new(&mut self))?;
         self.expect_ws(0x5D, ErrorCode::Syntax)?; // Can't fail
+        self.leave();
         Ok(value)
     }
 
@@ -785,8 +2019,11 @@ impl<'a, 'de> Deserializer<'de> for &'a mut JsonDeserializer<'de> {
         V: Visitor<'de>,
     {
         self.expect(0x7B, ErrorCode::ExpectedObject)?;
+        self.stats.objects += 1;
+        self.enter()?;
         let value = visitor.visit_map(CollectionAccessor::new(&mut self))?;
         self.expect_ws(0x7D, ErrorCode::Syntax)?; // Can't fail
+        self.leave();
         Ok(value)
     }
 
@@ -817,8 +2054,11 @@ impl<'a, 'de> Deserializer<'de> for &'a mut JsonDeserializer<'de> {
             visitor.visit_enum(self.parse_string()?.into_deserializer())
         } else if self.next()? == 0x7B {
             // Visit a newtype variant, tuple variant, or struct variant.
+            self.stats.objects += 1;
+            self.enter()?;
             let value = visitor.visit_enum(Enum::new(self))?;
             self.expect_ws(0x7D, ErrorCode::Syntax)?; // Can't fail
+            self.leave();
             Ok(value)
         } else {
             self.fail_at_position(ErrorCode::ExpectedEnum, pos)
@@ -843,11 +2083,18 @@ impl<'a, 'de> Deserializer<'de> for &'a mut JsonDeserializer<'de> {
 struct CollectionAccessor<'a, 'de> {
     des: &'a mut JsonDeserializer<'de>,
     first: bool,
+    // The keys of the object decoded so far, to detect duplicates. Keys without escape
+    // sequences are borrowed from the input.
+    keys: HashSet<Cow<'de, str>>,
 }
 
 impl<'a, 'de> CollectionAccessor<'a, 'de> {
     fn new(des: &'a mut JsonDeserializer<'de>) -> CollectionAccessor<'a, 'de> {
-        CollectionAccessor { des, first: true }
+        CollectionAccessor {
+            des,
+            first: true,
+            keys: HashSet::new(),
+        }
     }
 }
 
@@ -870,9 +2117,14 @@ impl<'a, 'de> SeqAccess<'de> for CollectionAccessor<'a, 'de> {
             self.des.expect_ws(0x2C, ErrorCode::Comma)?;
         }
 
-        self.des.peek_ws()?;
+        // in lenient mode, the last item may be followed by a `,`
+        if self.des.peek_ws()? == 0x5D && self.des.lenient {
+            return Ok(None);
+        }
+
+        self.des.count_element()?;
 
-        seed.deserialize(&mut *self.des).map(Some)
+        self.des.deserialize_located(seed).map(Some)
     }
 }
 
@@ -895,8 +2147,30 @@ impl<'a, 'de> MapAccess<'de> for CollectionAccessor<'a, 'de> {
             self.des.expect_ws(0x2C, ErrorCode::Comma)?;
         }
 
-        self.des.peek_ws()?;
-        seed.deserialize(&mut *self.des).map(Some)
+        // in lenient mode, the last entry may be followed by a `,`
+        if self.des.peek_ws()? == 0x7D && self.des.lenient {
+            return Ok(None);
+        }
+
+        self.des.count_element()?;
+
+        // Keys are decoded here rather than by the seed, so that duplicates can be detected
+        // regardless of the type being deserialized.
+        let pos = self.des.position();
+        let key = match self.des.parse_borrowed_string() {
+            Some(key) => Cow::Borrowed(key),
+            None => Cow::Owned(self.des.parse_string()?),
+        };
+        if self.keys.contains(&key) {
+            return self.des.fail_at_position(ErrorCode::DuplicateKey, pos);
+        }
+
+        let value: Result<K::Value, DecodeJsonError> = match key {
+            Cow::Borrowed(key) => seed.deserialize(BorrowedStrDeserializer::new(key)),
+            Cow::Owned(ref key) => seed.deserialize(key.as_str().into_deserializer()),
+        };
+        self.keys.insert(key);
+        value.map(Some)
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
@@ -905,8 +2179,7 @@ impl<'a, 'de> MapAccess<'de> for CollectionAccessor<'a, 'de> {
     {
         self.des.expect_ws(0x3A, ErrorCode::Colon)?; // `:`
 
-        self.des.peek_ws()?;
-        seed.deserialize(&mut *self.des)
+        self.des.deserialize_located(seed)
     }
 }
 
@@ -972,3 +2245,524 @@ impl<'a, 'de> VariantAccess<'de> for Enum<'a, 'de> {
         de::Deserializer::deserialize_map(self.des, visitor)
     }
 }
+
+#[test]
+fn test_negative_zero() {
+    use super::super::value::Value;
+
+    for input in &[&b"-0"[..], b"-0.0", b"-0e5", b"-0.0E-3"] {
+        assert_eq!(
+            from_slice::<Value>(input).unwrap_err().code,
+            ErrorCode::InvalidNumber
+        );
+    }
+
+    let zero = Value::Float(LegacyF64::from_f64(0.0).unwrap());
+    assert_eq!(from_slice::<Value>(b"0").unwrap(), zero);
+    assert_eq!(from_slice::<Value>(b"0.0").unwrap(), zero);
+    assert_eq!(
+        from_slice::<Value>(b"-1").unwrap(),
+        Value::Float(LegacyF64::from_f64(-1.0).unwrap())
+    );
+}
+
+#[test]
+fn test_integer_bounds() {
+    assert_eq!(from_slice::<u64>(b"9007199254740992"), Ok(9007199254740992));
+    assert_eq!(from_slice::<i64>(b"9007199254740992"), Ok(9007199254740992));
+    assert_eq!(
+        from_slice::<i64>(b"-9007199254740992"),
+        Ok(-9007199254740992)
+    );
+
+    assert_eq!(
+        from_slice::<u64>(b"9007199254740994").unwrap_err().code,
+        ErrorCode::OutOfBoundsU64
+    );
+    assert_eq!(
+        from_slice::<i64>(b"9007199254740994").unwrap_err().code,
+        ErrorCode::OutOfBoundsI64
+    );
+    assert_eq!(
+        from_slice::<i64>(b"-9007199254740994").unwrap_err().code,
+        ErrorCode::OutOfBoundsI64
+    );
+}
+
+#[test]
+fn test_decode_stats() {
+    use super::super::value::Value;
+
+    let input = br#"{"type": "post", "mentions": [{"link": "@foo"}, []], "n": [[[1]]]} "#;
+    let (_, stats) = from_slice_with_stats::<Value>(input).unwrap();
+
+    assert_eq!(
+        stats,
+        DecodeStats {
+            max_depth: 4,
+            objects: 2,
+            arrays: 5,
+            strings: 6,
+            bytes: input.len(),
+        }
+    );
+}
+
+#[test]
+fn test_unit() {
+    assert_eq!(from_slice::<()>(b"null"), Ok(()));
+
+    for input in &[&b"false"[..], b"0", b"\"\"", b"[]", b"{}"] {
+        assert_eq!(
+            from_slice::<()>(input).unwrap_err().code,
+            ErrorCode::ExpectedNull
+        );
+    }
+}
+
+#[test]
+fn test_recovering() {
+    assert_eq!(
+        from_slice_recovering(br#"{"a": [1, "b"]}"#),
+        from_slice::<Value>(br#"{"a": [1, "b"]}"#).map_err(|e| vec![e])
+    );
+
+    let errs = from_slice_recovering(b"[\"a\x01b\", {\"a\": 1, \"a\": 2}, [}, 3").unwrap_err();
+    let codes: Vec<_> = errs.into_iter().map(|e| e.code).collect();
+    assert_eq!(
+        codes,
+        vec![
+            ErrorCode::UnescapedControlCodePoint,
+            ErrorCode::DuplicateKey,
+            ErrorCode::Syntax,
+            ErrorCode::UnexpectedEndOfInput,
+        ]
+    );
+}
+
+#[test]
+fn test_subnormals() {
+    use super::to_string;
+
+    let cases: &[(&str, u64)] = &[
+        ("5e-324", 0x0000_0000_0000_0001),
+        ("-5e-324", 0x8000_0000_0000_0001),
+        ("1e-323", 0x0000_0000_0000_0002),
+        ("2.225073858507201e-308", 0x000F_FFFF_FFFF_FFFF),
+        ("2.2250738585072014e-308", 0x0010_0000_0000_0000),
+    ];
+
+    for &(input, bits) in cases {
+        let value = from_slice::<Value>(input.as_bytes()).unwrap();
+        assert_eq!(
+            value,
+            Value::Float(LegacyF64::from_f64(f64::from_bits(bits)).unwrap())
+        );
+        assert_eq!(to_string(&value, false).unwrap(), input);
+    }
+}
+
+#[test]
+fn test_lenient_trailing_commas() {
+    fn lenient(input: &[u8]) -> Result<Value, DecodeJsonError> {
+        let mut de = JsonDeserializer::from_slice(input).with_lenient(true);
+        let value = Value::deserialize(&mut de)?;
+        de.end().map(|_| value)
+    }
+
+    for &(with_comma, without_comma) in &[
+        (&b"[1,2,]"[..], &b"[1,2]"[..]),
+        (b"{\"a\":1,}", b"{\"a\":1}"),
+        (b"[[1 , ] , {\"a\": [], } ,\n]", b"[[1], {\"a\": []}]"),
+    ] {
+        assert_eq!(lenient(with_comma), from_slice::<Value>(without_comma));
+        assert!(from_slice::<Value>(with_comma).is_err());
+    }
+
+    assert!(lenient(b"[,]").is_err());
+    assert!(lenient(b"{,}").is_err());
+    assert!(lenient(b"[1,,]").is_err());
+}
+
+#[test]
+fn test_lenient_comments() {
+    fn lenient(input: &[u8]) -> Result<Value, DecodeJsonError> {
+        let mut de = JsonDeserializer::from_slice(input).with_lenient(true);
+        let value = Value::deserialize(&mut de)?;
+        de.end().map(|_| value)
+    }
+
+    for &(with_comments, without_comments) in &[
+        (
+            &b"// leading\n[1, // line\n2]// trailing"[..],
+            &b"[1, 2]"[..],
+        ),
+        (
+            b"/* a */{/**/\"a\" /* b\n */: /*c*/1/***/}/* d */",
+            b"{\"a\": 1}",
+        ),
+    ] {
+        assert_eq!(
+            lenient(with_comments),
+            from_slice::<Value>(without_comments)
+        );
+    }
+
+    let in_strings = b"[\"// not a comment\", \"/* nor this */\"]";
+    assert_eq!(lenient(in_strings), from_slice::<Value>(in_strings));
+
+    assert!(from_slice::<Value>(b"[1, // line\n2]").is_err());
+    assert!(from_slice::<Value>(b"/* a */ 1").is_err());
+    assert!(lenient(b"[1 /* unterminated ]").is_err());
+    assert!(lenient(b"[1 / 2]").is_err());
+}
+
+#[test]
+fn test_collections() {
+    use std::collections::HashMap;
+
+    let map = from_slice::<HashMap<String, LegacyF64>>(br#"{"a": 1, "b": 2.5}"#).unwrap();
+    assert_eq!(map.len(), 2);
+    assert_eq!(map["b"], LegacyF64::from_f64(2.5).unwrap());
+
+    assert_eq!(
+        from_slice::<Vec<String>>(br#"["a", "b"]"#),
+        Ok(vec!["a".to_string(), "b".to_string()])
+    );
+
+    assert_eq!(
+        from_slice::<HashMap<String, LegacyF64>>(br#"{"a": 1, "a": 2}"#),
+        Err(DecodeJsonError {
+            code: ErrorCode::DuplicateKey,
+            position: 9,
+        })
+    );
+    assert_eq!(
+        from_slice::<HashMap<String, LegacyF64>>(br#"{"a": 1, "\u0061": 2}"#),
+        Err(DecodeJsonError {
+            code: ErrorCode::DuplicateKey,
+            position: 9,
+        })
+    );
+    assert_eq!(
+        from_slice::<Vec<String>>(br#"["a", 1]"#).unwrap_err().code,
+        ErrorCode::ExpectedString
+    );
+
+    // Keys without escape sequences are borrowed from the input.
+    let map = from_slice::<HashMap<&str, bool>>(br#"{"a": true, "b": false}"#).unwrap();
+    assert!(map["a"]);
+    assert!(from_slice::<HashMap<&str, bool>>(br#"{"\u0061": true}"#).is_err());
+}
+
+#[test]
+fn test_spanned() {
+    let input = br#" {"a/b": {"~": null}, "c": [true, -1.5e3], "d": []} "#;
+    let (val, spans) = from_slice_spanned(input).unwrap();
+    assert_eq!(val, from_slice::<Value>(input).unwrap());
+
+    let text = |pointer: &str| &input[spans[pointer].clone()];
+    assert_eq!(text(""), &input[1..input.len() - 1]);
+    assert_eq!(text("/a~1b"), br#"{"~": null}"#);
+    assert_eq!(text("/a~1b/~0"), b"null");
+    assert_eq!(text("/c"), b"[true, -1.5e3]");
+    assert_eq!(text("/c/0"), b"true");
+    assert_eq!(text("/c/1"), b"-1.5e3");
+    assert_eq!(text("/d"), b"[]");
+    assert_eq!(spans.len(), 7);
+
+    assert_eq!(
+        from_slice_spanned(br#"{"a": 1, "a": 2}"#).unwrap_err(),
+        DecodeJsonError {
+            code: ErrorCode::DuplicateKey,
+            position: 9,
+        }
+    );
+    assert_eq!(
+        from_slice_spanned(b"[1 2]").unwrap_err().code,
+        ErrorCode::Comma
+    );
+}
+
+#[test]
+fn test_buffered_reader() {
+    // Yields the input one byte at a time.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> io::Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = io::BufRead::fill_buf(self)?.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            io::BufRead::consume(self, n);
+            Ok(n)
+        }
+    }
+
+    impl<'a> io::BufRead for Trickle<'a> {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            Ok(&self.0[..self.0.len().min(1)])
+        }
+
+        fn consume(&mut self, amt: usize) {
+            self.0 = &self.0[amt..];
+        }
+    }
+
+    let input = br#" 12 [{"a": "]\"}"}, []]true"x\\"-3e2"#;
+    let mut de = BufferedReaderDeserializer::new(Trickle(input));
+    assert_eq!(de.next_value::<u8>().unwrap(), 12);
+    assert_eq!(
+        de.next_value::<Value>().unwrap(),
+        from_slice::<Value>(br#"[{"a": "]\"}"}, []]"#).unwrap()
+    );
+    assert!(de.next_value::<bool>().unwrap());
+    assert_eq!(de.next_value::<String>().unwrap(), "x\\");
+    assert_eq!(de.next_value::<f64>().unwrap(), -300.0);
+    assert!(de.end().is_ok());
+    match de.next_value::<Value>() {
+        Err(ReadJsonError::Decode(e)) => assert!(e.is_recoverable()),
+        _ => panic!(),
+    }
+
+    let mut de = BufferedReaderDeserializer::new(Trickle(b"[1] [1 2] 3"));
+    assert!(de.next_value::<Value>().is_ok());
+    match de.next_value::<Value>() {
+        Err(ReadJsonError::Decode(e)) => assert_eq!((e.code, e.position), (ErrorCode::Comma, 7)),
+        _ => panic!(),
+    }
+
+    match from_reader::<Value, _>(Trickle(b"[1] 2")) {
+        Err(ReadJsonError::Decode(e)) => assert_eq!(e.position, 4),
+        _ => panic!(),
+    }
+}
+
+#[test]
+fn test_buffered_reader_leaves_rest_unread() {
+    let mut reader = io::BufReader::with_capacity(4, &b"[1] 23 {\"a\": true}\"b\"rest"[..]);
+
+    assert_eq!(
+        BufferedReaderDeserializer::new(&mut reader)
+            .next_value::<Vec<u8>>()
+            .unwrap(),
+        vec![1]
+    );
+    assert_eq!(
+        BufferedReaderDeserializer::new(&mut reader)
+            .next_value::<u8>()
+            .unwrap(),
+        23
+    );
+    assert!(BufferedReaderDeserializer::new(&mut reader)
+        .next_value::<Value>()
+        .unwrap()
+        .is_object());
+    assert_eq!(
+        BufferedReaderDeserializer::new(&mut reader)
+            .next_value::<String>()
+            .unwrap(),
+        "b"
+    );
+
+    let mut rest = String::new();
+    io::Read::read_to_string(&mut reader, &mut rest).unwrap();
+    assert_eq!(rest, "rest");
+
+    // Back-to-back length-prefixed frames from a single reader.
+    let mut frames = io::BufReader::with_capacity(3, &b"true[ 1]"[..]);
+    assert!(from_reader::<bool, _>(io::Read::take(&mut frames, 4)).unwrap());
+    assert_eq!(
+        from_reader::<Vec<u8>, _>(io::Read::take(&mut frames, 4)).unwrap(),
+        vec![1]
+    );
+}
+
+#[test]
+fn test_reject_noncharacters() {
+    let decode = |input: &[u8]| {
+        let mut de = JsonDeserializer::from_slice(input).with_reject_noncharacters(true);
+        String::deserialize(&mut de)
+    };
+    let noncharacter = |position| {
+        Err(DecodeJsonError {
+            code: ErrorCode::Noncharacter,
+            position,
+        })
+    };
+
+    assert_eq!(decode(br#""a\uFFFE""#), noncharacter(2));
+    assert_eq!(decode(br#""\uD83F\uDFFE""#), noncharacter(1));
+    assert_eq!(decode("\"ab\u{1FFFE}\"".as_bytes()), noncharacter(3));
+    assert_eq!(decode(br#""\uFDD0""#), noncharacter(1));
+    assert_eq!(decode(br#""\uFDEF""#), noncharacter(1));
+    assert_eq!(
+        decode(br#""\uFFFD\uFDF0""#),
+        Ok("\u{FFFD}\u{FDF0}".to_string())
+    );
+
+    assert_eq!(
+        from_slice::<String>(br#""\uD83F\uDFFE""#),
+        Ok("\u{1FFFE}".to_string())
+    );
+}
+
+#[test]
+fn test_ndjson() {
+    use super::write_ndjson;
+
+    let values = vec![
+        from_slice::<Value>(br#"{"a": "b\nc"}"#).unwrap(),
+        Value::Null,
+        from_slice::<Value>(b"[1, 2.5]").unwrap(),
+    ];
+    let mut out = Vec::new();
+    write_ndjson(&mut out, values.clone()).unwrap();
+    assert_eq!(out, b"{\"a\":\"b\\nc\"}\nnull\n[1,2.5]\n".to_vec());
+
+    let decoded: Result<Vec<_>, _> = read_ndjson(&out[..]).collect();
+    assert_eq!(decoded.unwrap(), values);
+
+    let mut empty = Vec::new();
+    write_ndjson(&mut empty, vec![]).unwrap();
+    assert!(empty.is_empty());
+    assert_eq!(read_ndjson(&b" \n\r\n"[..]).count(), 0);
+
+    let mut iter = read_ndjson(&b"1\n\n[2,]\n3"[..]);
+    assert!(iter.next().unwrap().is_ok());
+    match iter.next().unwrap() {
+        Err(ReadJsonError::Decode(e)) => assert_eq!(e.position, 6),
+        _ => panic!(),
+    }
+    assert!(iter.next().unwrap().is_ok());
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn test_error_category() {
+    let mut de = JsonDeserializer::from_slice(b"[1, 2, 3]").with_max_total_elements(2);
+    let err = Value::deserialize(&mut de).unwrap_err();
+    assert_eq!(err.category(), ErrorCategory::ResourceLimit);
+
+    let err = from_slice::<Value>(b"-0").unwrap_err();
+    assert_eq!(err.category(), ErrorCategory::Malformed);
+    assert_eq!(
+        ReadJsonError::Decode(err).category(),
+        ErrorCategory::Malformed
+    );
+
+    let err = io::Error::other("oh no");
+    assert_eq!(ReadJsonError::Io(err).category(), ErrorCategory::Io);
+}
+
+#[test]
+fn test_max_depth() {
+    let deep = vec![0x5B; 100_000];
+
+    let err = from_slice::<Value>(&deep).unwrap_err();
+    assert_eq!(err.code, ErrorCode::DepthLimitExceeded);
+    assert_eq!(err.position, DEFAULT_MAX_DEPTH);
+    let err = from_slice_spanned(&deep).unwrap_err();
+    assert_eq!(err.code, ErrorCode::DepthLimitExceeded);
+    let errs = from_slice_recovering(&deep).unwrap_err();
+    assert_eq!(errs[0].code, ErrorCode::DepthLimitExceeded);
+
+    let mut nested = vec![0x5B; DEFAULT_MAX_DEPTH];
+    nested.extend(vec![0x5D; DEFAULT_MAX_DEPTH]);
+    assert!(from_slice::<Value>(&nested).is_ok());
+    assert!(from_slice_spanned(&nested).is_ok());
+    assert!(from_slice_recovering(&nested).is_ok());
+
+    let mut de = JsonDeserializer::from_slice(br#"[{"a": {}}, [[2]], 3]"#).with_max_depth(2);
+    let mut errs = vec![];
+    let val = de.recover_value(&mut errs).unwrap();
+    let positions: Vec<_> = errs.iter().map(|e| e.position).collect();
+    assert_eq!(positions, vec![7, 13]);
+    assert_eq!(val, from_slice::<Value>(br#"[{}, [], 3]"#).unwrap());
+}
+
+#[test]
+fn test_custom_error_position() {
+    #[derive(Debug)]
+    struct Even;
+
+    impl<'de> Deserialize<'de> for Even {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Even, D::Error> {
+            match u8::deserialize(deserializer)? {
+                n if n % 2 == 0 => Ok(Even),
+                _ => Err(de::Error::custom("odd")),
+            }
+        }
+    }
+
+    let err = from_slice::<Vec<Even>>(b"[2, 4, 5, 6]").unwrap_err();
+    assert_eq!(err.code, ErrorCode::Message("odd".to_string()));
+    assert_eq!(err.position, 7);
+    assert_eq!(err.to_string(), "Message(\"odd\") at byte 7");
+
+    let err = from_slice::<Vec<Vec<Even>>>(b"[[], [0, 1]]").unwrap_err();
+    assert_eq!(err.position, 9);
+    let err = from_slice::<Even>(b"  3").unwrap_err();
+    assert_eq!(err.position, 2);
+}
+
+#[test]
+fn test_line_column() {
+    let at = |position| DecodeJsonError {
+        code: ErrorCode::Syntax,
+        position,
+    };
+
+    assert_eq!(at(0).line_column(b""), (1, 1));
+    assert_eq!(at(2).line_column(b"ab\ncd"), (1, 3));
+    assert_eq!(at(3).line_column(b"ab\ncd"), (2, 1));
+    assert_eq!(at(4).line_column(b"a\r\nb"), (2, 2));
+    assert_eq!(at(3).line_column(b"a\rb"), (2, 2));
+    assert_eq!(at(4).line_column(b"\n\r\r\n"), (4, 1));
+    assert_eq!(at(5).line_column("\"\u{e4}\u{e4}\"".as_bytes()), (1, 4));
+    assert_eq!(at(99).line_column(b"a\nb"), (2, 2));
+}
+
+#[test]
+fn test_borrowed_str() {
+    let input = "[\"abc\", \"\", \"\\u00e4\", \"\u{e4}\"]";
+    let decoded: Vec<String> = from_slice(input.as_bytes()).unwrap();
+    assert_eq!(decoded, vec!["abc", "", "\u{e4}", "\u{e4}"]);
+
+    // Only strings without escape sequences can be borrowed.
+    let input = "[\"abc\", \"\", \"\u{e4}\"]";
+    let decoded: Vec<&str> = from_slice(input.as_bytes()).unwrap();
+    assert_eq!(decoded, vec!["abc", "", "\u{e4}"]);
+    assert!(from_slice::<&str>(br#""\u00e4""#).is_err());
+
+    // Errors are still reported by the regular string decoder.
+    let err = from_slice::<&str>(b"\"a\x01\"").unwrap_err();
+    assert_eq!(err.code, ErrorCode::UnescapedControlCodePoint);
+    assert_eq!(err.position, 2);
+    let err = from_slice::<&str>(b"\"a\xff\"").unwrap_err();
+    assert_eq!(err.code, ErrorCode::InvalidUtf8String);
+    assert_eq!(err.position, 2);
+    let err = from_slice::<&str>(b"\"abc").unwrap_err();
+    assert_eq!(err.code, ErrorCode::UnexpectedEndOfInput);
+}
+
+#[test]
+fn test_skip_bom() {
+    let input = b"\xEF\xBB\xBF [true]";
+
+    let err = from_slice::<Value>(input).unwrap_err();
+    assert_eq!(err.code, ErrorCode::Syntax);
+    assert_eq!(err.position, 0);
+
+    let mut de = JsonDeserializer::from_slice(input).with_skip_bom(true);
+    assert_eq!(Vec::<bool>::deserialize(&mut de), Ok(vec![true]));
+    assert_eq!(de.end(), Ok(()));
+
+    // Positions include the byte order mark.
+    let err = from_slice_lenient::<Value>(b"\xEF\xBB\xBF[tru]").unwrap_err();
+    assert_eq!(err.position, 4);
+
+    // Only a single, leading byte order mark is skipped.
+    assert!(from_slice_lenient::<Value>(b" \xEF\xBB\xBFnull").is_err());
+    assert!(from_slice_lenient::<Value>(b"\xEF\xBB\xBF\xEF\xBB\xBFnull").is_err());
+    assert!(from_slice_lenient::<Value>(b"\xEF\xBB").is_err());
+}
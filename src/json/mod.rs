@@ -7,8 +7,16 @@
 
 mod de;
 mod ser;
+mod validate;
 
-pub use self::de::{from_slice, from_slice_partial, DecodeJsonError, ErrorCode, JsonDeserializer};
+pub use self::de::{
+    from_reader, from_slice, from_slice_lenient, from_slice_partial, from_slice_recovering,
+    from_slice_spanned, from_slice_with_stats, read_ndjson, BufferedReaderDeserializer,
+    DecodeJsonError, DecodeStats, DisplayInInput, ErrorCategory, ErrorCode, JsonDeserializer,
+    NdjsonIter, ReadJsonError, SpanMap, DEFAULT_MAX_DEPTH,
+};
 pub use self::ser::{
-    to_string, to_vec, to_writer, to_writer_indent, EncodeJsonError, JsonSerializer,
+    to_string, to_string_compact, to_string_signing, to_vec, to_writer, to_writer_counted,
+    to_writer_indent, write_ndjson, EncodeJsonError, JsonSerializer,
 };
+pub use self::validate::{validate_signing, ValidationReport};
@@ -7,10 +7,12 @@ use serde::ser::{
     SerializeTuple, SerializeTupleStruct, SerializeTupleVariant, Serializer,
 };
 
+use super::super::value::Value;
 use super::super::{is_i64_valid, is_u64_valid, LegacyF64};
 
 /// Everything that can go wrong during json serialization.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum EncodeJsonError {
     /// An IO error occured on the underlying writer.
     ///
@@ -18,11 +20,11 @@ pub enum EncodeJsonError {
     Io(io::Error),
     /// Tried to serialize a number forbidden by the ssb data format (an inifinity, NaN or -0.0).
     InvalidFloat(f64),
-    /// Tried to serialize an unsigned integer larger than 2^53 (these are not
+    /// Tried to serialize an unsigned integer larger than `MAX_SAFE_INTEGER` (these are not
     /// guaranteed to be represented correctly in a 64 bit float).
     InvalidUnsignedInteger(u64),
-    /// Tried to serialize an signed integer with absolute value larger than 2^53 (these are not
-    /// guaranteed to be represented correctly in a 64 bit float).
+    /// Tried to serialize an signed integer with absolute value larger than `MAX_SAFE_INTEGER`
+    /// (these are not guaranteed to be represented correctly in a 64 bit float).
     InvalidSignedInteger(i64),
     /// Can only serialize collections whose length is known upfront.
     UnknownLength,
@@ -51,11 +53,21 @@ impl From<io::Error> for EncodeJsonError {
 }
 
 /// A structure for serializing data into the legacy json encoding.
+///
+/// If serialization fails, the serializer is left in an inconsistent state (in particular,
+/// its indentation level is off). It must not be used again until [`reset`](#method.reset)
+/// has been called.
 pub struct JsonSerializer<W> {
     writer: W,
     // If true omits whitespace, else produces the signing format.
     compact: bool,
     indent: usize,
+    // The indentation level the serializer was created with.
+    base_indent: usize,
+    // What to write per indentation level.
+    indent_unit: &'static [u8],
+    // Whether to escape U+2028 and U+2029 in strings.
+    escape_line_separators: bool,
 }
 
 impl<W> JsonSerializer<W>
@@ -74,18 +86,61 @@ where
             writer,
             compact,
             indent,
+            base_indent: indent,
+            indent_unit: b"  ",
+            escape_line_separators: false,
         }
     }
 
+    /// Sets what to write per level of indentation when not `compact`, the default is two
+    /// spaces.
+    ///
+    /// The signing encoding uses two spaces, so never use any other unit for signing or
+    /// signature checking.
+    ///
+    /// ```
+    /// extern crate serde;
+    /// extern crate ssb_legacy_msg_data;
+    ///
+    /// use serde::Serialize;
+    /// use ssb_legacy_msg_data::json::JsonSerializer;
+    ///
+    /// let mut ser = JsonSerializer::new(Vec::new(), false, 0).with_indent(b"\t");
+    /// vec![1, 2].serialize(&mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), b"[\n\t1,\n\t2\n]");
+    /// ```
+    pub fn with_indent(mut self, unit: &'static [u8]) -> Self {
+        self.indent_unit = unit;
+        self
+    }
+
+    /// If `escape` is `true`, the line separator U+2028 and the paragraph separator U+2029 are
+    /// written as the escape sequences `\u2028` and `\u2029` rather than as raw utf8. This
+    /// makes the output safe for embedding into javascript source code, e.g. in a `<script>`
+    /// tag.
+    ///
+    /// The output is then no longer in the signing encoding, so never use this for signing or
+    /// signature checking.
+    pub fn with_escaped_line_separators(mut self, escape: bool) -> Self {
+        self.escape_line_separators = escape;
+        self
+    }
+
+    /// Restores the serializer to the state it was created in, so that it can be used again
+    /// after serialization failed. This does not affect data that has already been written.
+    pub fn reset(&mut self) {
+        self.indent = self.base_indent;
+    }
+
     /// Unwrap the `Writer` from the `Serializer`.
     pub fn into_inner(self) -> W {
         self.writer
     }
 
-    // Writes the correct number of spaces as indentation.
+    // Writes the indentation unit once per indentation level.
     fn write_indent(&mut self) -> Result<(), io::Error> {
         for _ in 0..self.indent {
-            self.writer.write_all(b"  ")?;
+            self.writer.write_all(self.indent_unit)?;
         }
         Ok(())
     }
@@ -97,9 +152,9 @@ where
     }
 
     fn end_object(&mut self) -> Result<(), io::Error> {
-        self.writer.write_all(b"}")?;
         self.indent -= 1;
-        Ok(())
+        self.newline()?;
+        self.writer.write_all(b"}")
     }
 
     fn begin_array(&mut self) -> Result<(), io::Error> {
@@ -125,6 +180,51 @@ where
         }
         Ok(())
     }
+
+    // Writes the contents of a string literal, escaping where necessary.
+    fn write_escaped(&mut self, s: &str) -> Result<(), io::Error> {
+        for byte in s.bytes() {
+            match byte {
+                0x00 => self.writer.write_all(br"\u0000")?,
+                0x01 => self.writer.write_all(br"\u0001")?,
+                0x02 => self.writer.write_all(br"\u0002")?,
+                0x03 => self.writer.write_all(br"\u0003")?,
+                0x04 => self.writer.write_all(br"\u0004")?,
+                0x05 => self.writer.write_all(br"\u0005")?,
+                0x06 => self.writer.write_all(br"\u0006")?,
+                0x07 => self.writer.write_all(br"\u0007")?,
+                0x08 => self.writer.write_all(br"\b")?,
+                0x09 => self.writer.write_all(br"\t")?,
+                0x0A => self.writer.write_all(br"\n")?,
+                0x0B => self.writer.write_all(br"\u000b")?,
+                0x0C => self.writer.write_all(br"\f")?,
+                0x0D => self.writer.write_all(br"\r")?,
+                0x0E => self.writer.write_all(br"\u000e")?,
+                0x0F => self.writer.write_all(br"\u000f")?,
+                0x10 => self.writer.write_all(br"\u0010")?,
+                0x11 => self.writer.write_all(br"\u0011")?,
+                0x12 => self.writer.write_all(br"\u0012")?,
+                0x13 => self.writer.write_all(br"\u0013")?,
+                0x14 => self.writer.write_all(br"\u0014")?,
+                0x15 => self.writer.write_all(br"\u0015")?,
+                0x16 => self.writer.write_all(br"\u0016")?,
+                0x17 => self.writer.write_all(br"\u0017")?,
+                0x18 => self.writer.write_all(br"\u0018")?,
+                0x19 => self.writer.write_all(br"\u0019")?,
+                0x1A => self.writer.write_all(br"\u001a")?,
+                0x1B => self.writer.write_all(br"\u001b")?,
+                0x1C => self.writer.write_all(br"\u001c")?,
+                0x1D => self.writer.write_all(br"\u001d")?,
+                0x1E => self.writer.write_all(br"\u001e")?,
+                0x1F => self.writer.write_all(br"\u001f")?,
+                0x22 => self.writer.write_all(b"\\\"")?,
+                0x5C => self.writer.write_all(br"\\")?,
+                other => self.writer.write_all(&[other])?,
+            }
+        }
+
+        Ok(())
+    }
 }
 
 /// Serialize the given data structure as JSON into the IO stream.
@@ -141,6 +241,72 @@ where
     value.serialize(&mut ser)
 }
 
+/// Write the given values as newline-delimited json: each value is serialized in compact form
+/// and followed by a newline (`0x0A`). Writes nothing if there are no values.
+///
+/// ```
+/// use ssb_legacy_msg_data::json::write_ndjson;
+/// use ssb_legacy_msg_data::value::Value;
+///
+/// let mut out = Vec::new();
+/// write_ndjson(&mut out, vec![Value::Null, Value::Array(vec![Value::Bool(true)])]).unwrap();
+/// assert_eq!(out, b"null\n[true]\n");
+/// ```
+pub fn write_ndjson<W, I>(writer: &mut W, values: I) -> Result<(), EncodeJsonError>
+where
+    W: io::Write,
+    I: IntoIterator<Item = Value>,
+{
+    for value in values {
+        to_writer(writer, &value, true)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Serialize the given data structure as JSON into the IO stream, flush it, and return the
+/// number of bytes written.
+///
+/// ```
+/// use ssb_legacy_msg_data::json::to_writer_counted;
+///
+/// let mut out = Vec::new();
+/// assert_eq!(to_writer_counted(&mut out, &vec![1, 2], false).unwrap(), 12);
+/// assert_eq!(out, b"[\n  1,\n  2\n]");
+/// ```
+pub fn to_writer_counted<W, T>(
+    writer: &mut W,
+    value: &T,
+    compact: bool,
+) -> Result<usize, EncodeJsonError>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    let mut counter = CountingWriter { writer, count: 0 };
+    to_writer(&mut counter, value, compact)?;
+    io::Write::flush(&mut counter)?;
+    Ok(counter.count)
+}
+
+// Wraps a writer and counts the bytes written to it.
+struct CountingWriter<W> {
+    writer: W,
+    count: usize,
+}
+
+impl<W: io::Write> io::Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        self.count += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
 /// Serialize the given data structure as JSON into the IO stream.
 pub fn to_writer_indent<W, T: ?Sized>(
     writer: &mut W,
@@ -176,6 +342,39 @@ where
     })
 }
 
+/// Serialize the given data structure as JSON into a `String` without any whitespace. Same as
+/// `to_string(value, true)`.
+///
+/// The output is *not* the signing encoding, use
+/// [`to_string_signing`](fn.to_string_signing.html) for signing or signature checking.
+///
+/// ```
+/// use ssb_legacy_msg_data::json::to_string_compact;
+///
+/// assert_eq!(to_string_compact(&vec![1, 2]).unwrap(), "[1,2]");
+/// ```
+pub fn to_string_compact<T>(value: &T) -> Result<String, EncodeJsonError>
+where
+    T: ?Sized + Serialize,
+{
+    to_string(value, true)
+}
+
+/// Serialize the given data structure into a `String` in the signing encoding, as used for
+/// computing and checking message signatures. Same as `to_string(value, false)`.
+///
+/// ```
+/// use ssb_legacy_msg_data::json::to_string_signing;
+///
+/// assert_eq!(to_string_signing(&vec![1, 2]).unwrap(), "[\n  1,\n  2\n]");
+/// ```
+pub fn to_string_signing<T>(value: &T) -> Result<String, EncodeJsonError>
+where
+    T: ?Sized + Serialize,
+{
+    to_string(value, false)
+}
+
 impl<'a, W> Serializer for &'a mut JsonSerializer<W>
 where
     W: io::Write,
@@ -268,44 +467,20 @@ where
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
         self.writer.write_all(b"\"")?;
 
-        for byte in v.bytes() {
-            match byte {
-                0x00 => self.writer.write_all(br"\u0000")?,
-                0x01 => self.writer.write_all(br"\u0001")?,
-                0x02 => self.writer.write_all(br"\u0002")?,
-                0x03 => self.writer.write_all(br"\u0003")?,
-                0x04 => self.writer.write_all(br"\u0004")?,
-                0x05 => self.writer.write_all(br"\u0005")?,
-                0x06 => self.writer.write_all(br"\u0006")?,
-                0x07 => self.writer.write_all(br"\u0007")?,
-                0x08 => self.writer.write_all(br"\b")?,
-                0x09 => self.writer.write_all(br"\t")?,
-                0x0A => self.writer.write_all(br"\n")?,
-                0x0B => self.writer.write_all(br"\u000b")?,
-                0x0C => self.writer.write_all(br"\f")?,
-                0x0D => self.writer.write_all(br"\r")?,
-                0x0E => self.writer.write_all(br"\u000e")?,
-                0x0F => self.writer.write_all(br"\u000f")?,
-                0x10 => self.writer.write_all(br"\u0010")?,
-                0x11 => self.writer.write_all(br"\u0011")?,
-                0x12 => self.writer.write_all(br"\u0012")?,
-                0x13 => self.writer.write_all(br"\u0013")?,
-                0x14 => self.writer.write_all(br"\u0014")?,
-                0x15 => self.writer.write_all(br"\u0015")?,
-                0x16 => self.writer.write_all(br"\u0016")?,
-                0x17 => self.writer.write_all(br"\u0017")?,
-                0x18 => self.writer.write_all(br"\u0018")?,
-                0x19 => self.writer.write_all(br"\u0019")?,
-                0x1A => self.writer.write_all(br"\u001a")?,
-                0x1B => self.writer.write_all(br"\u001b")?,
-                0x1C => self.writer.write_all(br"\u001c")?,
-                0x1D => self.writer.write_all(br"\u001d")?,
-                0x1E => self.writer.write_all(br"\u001e")?,
-                0x1F => self.writer.write_all(br"\u001f")?,
-                0x22 => self.writer.write_all(b"\\\"")?,
-                0x5C => self.writer.write_all(br"\\")?,
-                other => self.writer.write_all(&[other])?,
+        if self.escape_line_separators {
+            let mut last = 0;
+            for (i, separator) in v.match_indices(['\u{2028}', '\u{2029}']) {
+                self.write_escaped(&v[last..i])?;
+                if separator == "\u{2028}" {
+                    self.writer.write_all(br"\u2028")?;
+                } else {
+                    self.writer.write_all(br"\u2029")?;
+                }
+                last = i + separator.len();
             }
+            self.write_escaped(&v[last..])?;
+        } else {
+            self.write_escaped(v)?;
         }
 
         self.writer.write_all(b"\"").map_err(EncodeJsonError::Io)
@@ -377,7 +552,6 @@ where
         self.colon()?;
         value.serialize(&mut *self)?;
 
-        self.newline()?;
         self.end_object()?;
         Ok(())
     }
@@ -656,3 +830,217 @@ where
         Ok(())
     }
 }
+
+#[test]
+fn test_nested_indentation() {
+    use super::super::value::{RidiculousStringMap, Value};
+
+    fn nest(depth: usize) -> Value {
+        if depth == 0 {
+            Value::Float(LegacyF64::from_f64(1.0).unwrap())
+        } else if depth % 2 == 1 {
+            Value::Array(vec![
+                nest(depth - 1),
+                Value::Array(vec![]),
+                Value::Object(RidiculousStringMap::with_capacity(0)),
+            ])
+        } else {
+            let mut m = RidiculousStringMap::with_capacity(3);
+            m.insert("k".to_string(), nest(depth - 1));
+            m.insert(
+                "e".to_string(),
+                Value::Object(RidiculousStringMap::with_capacity(0)),
+            );
+            m.insert("0".to_string(), Value::Array(vec![]));
+            Value::Object(m)
+        }
+    }
+
+    // Output of `JSON.stringify(value, null, 2)`.
+    let expected = r#"{
+  "0": [],
+  "k": [
+    {
+      "0": [],
+      "k": [
+        {
+          "0": [],
+          "k": [
+            {
+              "0": [],
+              "k": [
+                {
+                  "0": [],
+                  "k": [
+                    1,
+                    [],
+                    {}
+                  ],
+                  "e": {}
+                },
+                [],
+                {}
+              ],
+              "e": {}
+            },
+            [],
+            {}
+          ],
+          "e": {}
+        },
+        [],
+        {}
+      ],
+      "e": {}
+    },
+    [],
+    {}
+  ],
+  "e": {}
+}"#;
+
+    assert_eq!(to_string(&nest(10), false).unwrap(), expected);
+}
+
+#[test]
+fn test_newtype_variant_indentation() {
+    #[derive(Serialize)]
+    enum Wrapper {
+        Wrapped(Vec<u32>),
+    }
+
+    assert_eq!(
+        to_string(&Wrapper::Wrapped(vec![1, 2]), false).unwrap(),
+        "{\n  \"Wrapped\": [\n    1,\n    2\n  ]\n}"
+    );
+}
+
+#[test]
+fn test_reset_after_io_error() {
+    use std::io::Write;
+
+    use super::super::value::Value;
+    use super::from_slice;
+
+    // A writer that fails once it would exceed `fail_at` bytes, and works normally afterwards.
+    struct FailOnce {
+        fail_at: Option<usize>,
+        out: Vec<u8>,
+    }
+
+    impl Write for FailOnce {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if let Some(n) = self.fail_at {
+                if self.out.len() + buf.len() > n {
+                    self.fail_at = None;
+                    return Err(io::Error::other("fail"));
+                }
+            }
+            self.out.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let value = from_slice::<Value>(br#"{"a": [1, {"b": [[]]}], "c": {}}"#).unwrap();
+    let expected = to_vec(&value, false).unwrap();
+
+    let mut ser = JsonSerializer::new(
+        FailOnce {
+            fail_at: Some(20),
+            out: vec![],
+        },
+        false,
+        0,
+    );
+    match value.serialize(&mut ser) {
+        Err(EncodeJsonError::Io(_)) => {}
+        _ => panic!("expected an io error"),
+    }
+
+    ser.reset();
+    value.serialize(&mut ser).unwrap();
+    assert!(ser.into_inner().out.ends_with(&expected));
+}
+
+#[test]
+fn test_escaped_line_separators() {
+    use super::super::value::Value;
+    use super::from_slice;
+
+    let value = Value::String("a\u{2028}b\u{2029}\u{2028}".to_string());
+
+    let mut ser = JsonSerializer::new(Vec::new(), true, 0).with_escaped_line_separators(true);
+    value.serialize(&mut ser).unwrap();
+    let escaped = ser.into_inner();
+
+    assert_eq!(&escaped[..], &br#""a\u2028b\u2029\u2028""#[..]);
+    assert_eq!(from_slice::<Value>(&escaped).unwrap(), value);
+
+    // The signing encoding uses raw utf8.
+    assert_eq!(
+        to_string(&value, false).unwrap(),
+        "\"a\u{2028}b\u{2029}\u{2028}\""
+    );
+}
+
+#[test]
+fn test_control_character_escapes() {
+    use super::super::value::Value;
+    use super::from_slice;
+
+    let value = Value::String((0x00u8..=0x1F).map(char::from).collect());
+
+    // Output of `JSON.stringify`.
+    let expected = concat!(
+        r#""\u0000\u0001\u0002\u0003\u0004\u0005\u0006\u0007\b\t\n\u000b\f\r\u000e\u000f"#,
+        r#"\u0010\u0011\u0012\u0013\u0014\u0015\u0016\u0017\u0018\u0019\u001a\u001b\u001c"#,
+        r#"\u001d\u001e\u001f""#
+    );
+
+    let encoded = to_string(&value, false).unwrap();
+    assert_eq!(encoded, expected);
+    assert_eq!(from_slice::<Value>(encoded.as_bytes()).unwrap(), value);
+}
+
+#[test]
+fn test_serialize_references() {
+    use super::super::value::Value;
+
+    let a = Value::Bool(true);
+    let b = Value::Null;
+
+    let owned = vec![a.clone(), b.clone(), a.clone()];
+    let refs: Vec<&Value> = vec![&a, &b, &a];
+    let boxes: Vec<_> = owned.iter().cloned().map(Box::new).collect();
+
+    let expected = to_vec(&owned, false).unwrap();
+    assert_eq!(to_vec(&refs, false).unwrap(), expected);
+    assert_eq!(to_vec(&boxes, false).unwrap(), expected);
+}
+
+#[test]
+fn test_indent_unit() {
+    use super::super::value::Value;
+    use super::from_slice;
+
+    let value = from_slice::<Value>(br#"{"a": [1, {"b": null}], "c": {}}"#).unwrap();
+    let signing = "{\n  \"a\": [\n    1,\n    {\n      \"b\": null\n    }\n  ],\n  \"c\": {}\n}";
+
+    // The default is the signing encoding.
+    let mut ser = JsonSerializer::new(Vec::new(), false, 0);
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(ser.into_inner(), signing.as_bytes());
+    assert_eq!(to_string(&value, false).unwrap(), signing);
+
+    let mut ser = JsonSerializer::new(Vec::new(), false, 0).with_indent(b"    ");
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(ser.into_inner(), signing.replace("  ", "    ").as_bytes());
+
+    // Compact output is unaffected.
+    let mut ser = JsonSerializer::new(Vec::new(), true, 0).with_indent(b"\t");
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(ser.into_inner(), to_vec(&value, true).unwrap());
+}
@@ -0,0 +1,255 @@
+use std::{error, fmt};
+
+use super::super::value::Value;
+use super::de::is_ws;
+use super::{from_slice_spanned, to_vec, DecodeJsonError, ErrorCode, SpanMap};
+
+/// Everything that can prevent some input from being a legacy value in
+/// [signing encoding](https://spec.scuttlebutt.nz/feed/datamodel.html#signing-encoding).
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[non_exhaustive]
+pub enum ValidationReport {
+    /// The input could not be decoded into a legacy value.
+    Decode(DecodeJsonError),
+    /// The input contained an object with the same key twice.
+    DuplicateKey,
+    /// The input is a valid legacy value, but its whitespace deviates from the signing encoding
+    /// at the given byte offset.
+    Whitespace {
+        /// Byte offset of the first deviation.
+        position: usize,
+    },
+    /// The input is a valid legacy value, but some object lists its entries in a different
+    /// order than the signing encoding (which puts entries with
+    /// [numeric keys](https://spec.scuttlebutt.nz/feed/datamodel.html#signing-encoding-objects)
+    /// first).
+    UnorderedKeys {
+        /// Byte offset of the value of the first entry in the input that is not in signing
+        /// order.
+        position: usize,
+    },
+    /// The input is a valid legacy value, but it deviates from the signing encoding at the
+    /// given byte offset in some other way than whitespace or entry order, e.g. by the
+    /// representation of numbers and strings.
+    NonCanonical {
+        /// Byte offset of the first deviation.
+        position: usize,
+    },
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> std::result::Result<(), fmt::Error> {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl error::Error for ValidationReport {}
+
+/// Check whether the input is a legacy value in
+/// [signing encoding](https://spec.scuttlebutt.nz/feed/datamodel.html#signing-encoding),
+/// reporting the reason if it is not.
+///
+/// ```
+/// use ssb_legacy_msg_data::json::{validate_signing, ValidationReport};
+///
+/// assert!(validate_signing(b"{\n  \"a\": 1\n}").is_ok());
+/// assert_eq!(
+///     validate_signing(b"[\n  1.0\n]"),
+///     Err(ValidationReport::NonCanonical { position: 5 })
+/// );
+/// assert_eq!(
+///     validate_signing(b"{\"a\": 1, \"b\": 2}"),
+///     Err(ValidationReport::Whitespace { position: 1 })
+/// );
+/// assert_eq!(
+///     validate_signing(b"{\n  \"a\": 1,\n  \"1\": 2\n}"),
+///     Err(ValidationReport::UnorderedKeys { position: 9 })
+/// );
+/// ```
+pub fn validate_signing(input: &[u8]) -> Result<(), ValidationReport> {
+    let (value, spans) = match from_slice_spanned(input) {
+        Ok(decoded) => decoded,
+        Err(DecodeJsonError {
+            code: ErrorCode::DuplicateKey,
+            ..
+        }) => return Err(ValidationReport::DuplicateKey),
+        Err(e) => return Err(ValidationReport::Decode(e)),
+    };
+
+    // Serializing a `Value` into a `Vec` never fails.
+    let expected = to_vec(&value, false).unwrap();
+
+    let position = match input.iter().zip(expected.iter()).position(|(a, b)| a != b) {
+        Some(position) => position,
+        None if input.len() == expected.len() => return Ok(()),
+        None => expected.len(),
+    };
+
+    if strip_ws(input) == to_vec(&value, true).unwrap() {
+        Err(ValidationReport::Whitespace { position })
+    } else if let Some(position) = first_unordered_entry(&value, &mut String::new(), &spans) {
+        Err(ValidationReport::UnorderedKeys { position })
+    } else {
+        Err(ValidationReport::NonCanonical { position })
+    }
+}
+
+// Finds the smallest input offset of an object entry (more precisely, of its value) whose
+// position within its object differs from the signing order. `pointer` points to `value`.
+fn first_unordered_entry(value: &Value, pointer: &mut String, spans: &SpanMap) -> Option<usize> {
+    let mut first = None;
+    let mut found = |position: Option<usize>| {
+        if let Some(position) = position {
+            first = Some(first.map_or(position, |first: usize| first.min(position)));
+        }
+    };
+
+    match *value {
+        Value::Array(ref v) => {
+            for (i, val) in v.iter().enumerate() {
+                let len = pointer.len();
+                pointer.push_str(&format!("/{}", i));
+                found(first_unordered_entry(val, pointer, spans));
+                pointer.truncate(len);
+            }
+        }
+        Value::Object(ref m) => {
+            // The entries in signing order, together with the offset of their values.
+            let mut entries = Vec::with_capacity(m.len());
+            for (key, val) in m {
+                let len = pointer.len();
+                pointer.push('/');
+                pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                entries.push((key, spans[pointer.as_str()].start));
+                found(first_unordered_entry(val, pointer, spans));
+                pointer.truncate(len);
+            }
+
+            let mut in_input_order = entries.clone();
+            in_input_order.sort_by_key(|&(_, start)| start);
+            found(
+                entries
+                    .iter()
+                    .zip(in_input_order.iter())
+                    .find(|&(signing, input)| signing.0 != input.0)
+                    .map(|(_, input)| input.1),
+            );
+        }
+        _ => {}
+    }
+
+    first
+}
+
+// Removes all whitespace outside of string literals from some valid json.
+fn strip_ws(input: &[u8]) -> Vec<u8> {
+    let mut stripped = Vec::with_capacity(input.len());
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for &byte in input {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if byte == 0x5C {
+                escaped = true;
+            } else if byte == 0x22 {
+                in_string = false;
+            }
+        } else if is_ws(byte) {
+            continue;
+        } else if byte == 0x22 {
+            in_string = true;
+        }
+
+        stripped.push(byte);
+    }
+
+    stripped
+}
+
+#[test]
+fn test_validate_signing() {
+    assert_eq!(
+        validate_signing(b"{\n  \"a\": [\n    1,\n    \"b\"\n  ]\n}"),
+        Ok(())
+    );
+    assert_eq!(validate_signing(b"{}"), Ok(()));
+
+    match validate_signing(b"[1,") {
+        Err(ValidationReport::Decode(e)) => assert_eq!(e.code, ErrorCode::UnexpectedEndOfInput),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn test_validate_duplicate_key() {
+    assert_eq!(
+        validate_signing(b"{\n  \"a\": 1,\n  \"a\": 2\n}"),
+        Err(ValidationReport::DuplicateKey)
+    );
+    assert_eq!(
+        validate_signing(b"[\n  {\n    \"0\": 1,\n    \"0\": 2\n  }\n]"),
+        Err(ValidationReport::DuplicateKey)
+    );
+}
+
+#[test]
+fn test_validate_whitespace() {
+    assert_eq!(
+        validate_signing(b"[1]"),
+        Err(ValidationReport::Whitespace { position: 1 })
+    );
+    assert_eq!(
+        validate_signing(b"{\n  \"a\":1\n}"),
+        Err(ValidationReport::Whitespace { position: 8 })
+    );
+    assert_eq!(
+        validate_signing(b"[\n  1\n]\n"),
+        Err(ValidationReport::Whitespace { position: 7 })
+    );
+}
+
+#[test]
+fn test_validate_unordered_keys() {
+    // Numeric keys come first, in numeric order.
+    assert_eq!(
+        validate_signing(b"{\n  \"a\": 1,\n  \"1\": 2\n}"),
+        Err(ValidationReport::UnorderedKeys { position: 9 })
+    );
+    assert_eq!(
+        validate_signing(b"{\n  \"10\": 1,\n  \"2\": 2\n}"),
+        Err(ValidationReport::UnorderedKeys { position: 10 })
+    );
+    assert_eq!(validate_signing(b"{\n  \"2\": 1,\n  \"10\": 2\n}"), Ok(()));
+
+    // Other keys keep their order.
+    assert_eq!(validate_signing(b"{\n  \"b\": 1,\n  \"a\": 2\n}"), Ok(()));
+
+    // Nested objects, and misordering combined with other deviations.
+    assert_eq!(
+        validate_signing(b"[\n  0,\n  {\n    \"x\": null,\n    \"0\": 1.0\n  }\n]"),
+        Err(ValidationReport::UnorderedKeys { position: 20 })
+    );
+    assert_eq!(
+        validate_signing(b"{\"a\":1,\"1\":2}"),
+        Err(ValidationReport::UnorderedKeys { position: 5 })
+    );
+}
+
+#[test]
+fn test_validate_non_canonical() {
+    for &(input, position) in &[
+        (&b"[\n  1.0\n]"[..], 5),
+        (b"[\n  1e2\n]", 5),
+        (b"[\n  0.10\n]", 7),
+        (b"[\n  100000000000000000000000\n]", 5),
+        (b"[\n  \"\\u0061\"\n]", 5),
+        (b"[\n  \"\\/\"\n]", 5),
+    ] {
+        assert_eq!(
+            validate_signing(input),
+            Err(ValidationReport::NonCanonical { position })
+        );
+    }
+}
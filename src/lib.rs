@@ -12,15 +12,27 @@ extern crate indexmap;
 extern crate ryu_ecmascript;
 extern crate serde;
 extern crate strtod2;
+#[cfg(test)]
 #[macro_use]
 extern crate serde_derive;
 extern crate base64;
+#[cfg(feature = "hash")]
+extern crate sha2;
 
+#[cfg(feature = "hash")]
+pub mod hash;
 pub mod json;
+pub mod prelude;
 pub mod value;
 
 use std::cmp::Ordering;
-use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::{Product, Sum};
+use std::ops::{Add, Mul, Neg, Sub};
+use std::{error, fmt};
+
+use serde::de::{self, Deserialize, Deserializer, Unexpected};
+use serde::{Serialize, Serializer};
 
 /// A wrapper around `f64` to indicate that the float is compatible with the ssb legacy message
 /// data model, i.e. it is [neither an infinity, nor `-0.0`, nor a `NaN`](https://spec.scuttlebutt.nz/feed/datamodel.html#floats).
@@ -28,11 +40,27 @@ use std::fmt;
 /// Because a `LegacyF64` is never `NaN`, it can implement `Eq` and `Ord`, which regular `f64`
 /// can not.
 ///
-/// To obtain the inner value, use the `From<LegacyF64> for f64` impl.
-#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+/// To obtain the inner value, use [`to_f64`](#method.to_f64) or the `From<LegacyF64> for f64`
+/// impl.
+///
+/// It (de)serializes as an `f64`, deserialization fails for floats that are not valid
+/// `LegacyF64`s.
+#[derive(Clone, Copy, PartialEq, Default)]
 pub struct LegacyF64(f64);
 
 impl LegacyF64 {
+    /// Zero (`+0.0`, since `-0.0` is not a valid `LegacyF64`).
+    pub const ZERO: LegacyF64 = LegacyF64(0.0);
+
+    /// One.
+    pub const ONE: LegacyF64 = LegacyF64(1.0);
+
+    /// The largest `LegacyF64`, equal to `f64::MAX`.
+    pub const MAX: LegacyF64 = LegacyF64(f64::MAX);
+
+    /// The smallest `LegacyF64`, equal to `f64::MIN`.
+    pub const MIN: LegacyF64 = LegacyF64(f64::MIN);
+
     /// Safe conversion of an arbitrary `f64` into a `LegacyF64`.
     ///
     /// ```
@@ -85,11 +113,153 @@ impl LegacyF64 {
             f.is_finite() && (f != 0.0)
         }
     }
+
+    /// Converts an integer into a `LegacyF64`, returning `None` if it lies outside the range
+    /// from [`MIN_SAFE_INTEGER`](constant.MIN_SAFE_INTEGER.html) to
+    /// [`MAX_SAFE_INTEGER`](constant.MAX_SAFE_INTEGER.html), so that the conversion never
+    /// loses precision.
+    ///
+    /// ```
+    /// use ssb_legacy_msg_data::{LegacyF64, MIN_SAFE_INTEGER};
+    ///
+    /// assert_eq!(LegacyF64::from_i64(-3), LegacyF64::from_f64(-3.0));
+    /// assert!(LegacyF64::from_i64(MIN_SAFE_INTEGER - 1).is_none());
+    /// ```
+    pub fn from_i64(n: i64) -> Option<LegacyF64> {
+        if is_i64_valid(n) {
+            Some(LegacyF64(n as f64))
+        } else {
+            None
+        }
+    }
+
+    /// Converts an integer into a `LegacyF64`, returning `None` if it is greater than
+    /// [`MAX_SAFE_INTEGER`](constant.MAX_SAFE_INTEGER.html), so that the conversion never
+    /// loses precision.
+    ///
+    /// ```
+    /// use ssb_legacy_msg_data::{LegacyF64, MAX_SAFE_INTEGER};
+    ///
+    /// assert_eq!(LegacyF64::from_u64(3), LegacyF64::from_f64(3.0));
+    /// assert!(LegacyF64::from_u64(MAX_SAFE_INTEGER + 1).is_none());
+    /// ```
+    pub fn from_u64(n: u64) -> Option<LegacyF64> {
+        if is_u64_valid(n) {
+            Some(LegacyF64(n as f64))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the inner `f64`.
+    ///
+    /// ```
+    /// use ssb_legacy_msg_data::LegacyF64;
+    ///
+    /// assert_eq!(LegacyF64::from_f64(16.0).unwrap().to_f64().sqrt(), 4.0);
+    /// ```
+    pub fn to_f64(self) -> f64 {
+        self.0
+    }
+
+    /// Adds two `LegacyF64`s, returning `None` if the result is not a valid `LegacyF64`.
+    ///
+    /// ```
+    /// use ssb_legacy_msg_data::LegacyF64;
+    ///
+    /// let max = LegacyF64::from_f64(std::f64::MAX).unwrap();
+    /// assert!(max.checked_add(max).is_none());
+    /// ```
+    pub fn checked_add(self, other: LegacyF64) -> Option<LegacyF64> {
+        LegacyF64::from_f64(self.0 + other.0)
+    }
+
+    /// Subtracts two `LegacyF64`s, returning `None` if the result is not a valid `LegacyF64`.
+    pub fn checked_sub(self, other: LegacyF64) -> Option<LegacyF64> {
+        LegacyF64::from_f64(self.0 - other.0)
+    }
+
+    /// Multiplies two `LegacyF64`s, returning `None` if the result is not a valid `LegacyF64`.
+    ///
+    /// Note that this includes a result of `-0.0`, e.g. when multiplying zero with a
+    /// negative number.
+    pub fn checked_mul(self, other: LegacyF64) -> Option<LegacyF64> {
+        LegacyF64::from_f64(self.0 * other.0)
+    }
+
+    /// Truncates this float towards zero and clamps it into the range from
+    /// [`MIN_SAFE_INTEGER`](constant.MIN_SAFE_INTEGER.html) to
+    /// [`MAX_SAFE_INTEGER`](constant.MAX_SAFE_INTEGER.html), so that it can be used as an
+    /// integer.
+    ///
+    /// ```
+    /// use ssb_legacy_msg_data::{LegacyF64, MAX_SAFE_INTEGER};
+    ///
+    /// assert_eq!(LegacyF64::from_f64(-2.7).unwrap().clamp_to_safe_integer(), -2);
+    /// assert_eq!(
+    ///     LegacyF64::from_f64(1e300).unwrap().clamp_to_safe_integer(),
+    ///     MAX_SAFE_INTEGER as i64
+    /// );
+    /// ```
+    pub fn clamp_to_safe_integer(&self) -> i64 {
+        if self.0 >= MAX_SAFE_INTEGER as f64 {
+            MAX_SAFE_INTEGER as i64
+        } else if self.0 <= MIN_SAFE_INTEGER as f64 {
+            MIN_SAFE_INTEGER
+        } else {
+            self.0 as i64
+        }
+    }
+}
+
+/// Sums up `LegacyF64`s, yielding `None` if any intermediate result is not a valid `LegacyF64`.
+///
+/// ```
+/// use ssb_legacy_msg_data::LegacyF64;
+///
+/// let nums = vec![LegacyF64::from_f64(1.5).unwrap(), LegacyF64::from_f64(2.0).unwrap()];
+/// assert_eq!(nums.iter().copied().sum::<Option<LegacyF64>>(), LegacyF64::from_f64(3.5));
+///
+/// let huge = vec![LegacyF64::from_f64(std::f64::MAX).unwrap(); 2];
+/// assert_eq!(huge.iter().copied().sum::<Option<LegacyF64>>(), None);
+/// ```
+impl Sum<LegacyF64> for Option<LegacyF64> {
+    fn sum<I: Iterator<Item = LegacyF64>>(mut iter: I) -> Self {
+        iter.try_fold(LegacyF64::ZERO, |acc, f| acc.checked_add(f))
+    }
+}
+
+/// Multiplies `LegacyF64`s, yielding `None` if any intermediate result is not a valid
+/// `LegacyF64`.
+///
+/// ```
+/// use ssb_legacy_msg_data::LegacyF64;
+///
+/// let nums = vec![LegacyF64::from_f64(1.5).unwrap(), LegacyF64::from_f64(-2.0).unwrap()];
+/// assert_eq!(nums.iter().copied().product::<Option<LegacyF64>>(), LegacyF64::from_f64(-3.0));
+///
+/// let huge = vec![LegacyF64::from_f64(1e200).unwrap(); 2];
+/// assert_eq!(huge.iter().copied().product::<Option<LegacyF64>>(), None);
+/// ```
+impl Product<LegacyF64> for Option<LegacyF64> {
+    fn product<I: Iterator<Item = LegacyF64>>(mut iter: I) -> Self {
+        iter.try_fold(LegacyF64::ONE, |acc, f| acc.checked_mul(f))
+    }
 }
 
+/// Formats the float exactly as the json encoding writes it (which is how javascript's
+/// `Number.prototype.toString` formats numbers).
+///
+/// ```
+/// use ssb_legacy_msg_data::LegacyF64;
+///
+/// assert_eq!(LegacyF64::from_f64(1.0).unwrap().to_string(), "1");
+/// assert_eq!(LegacyF64::from_f64(1e21).unwrap().to_string(), "1e+21");
+/// ```
 impl fmt::Display for LegacyF64 {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        self.0.fmt(f)
+        let mut buffer = ryu_ecmascript::Buffer::new();
+        f.write_str(buffer.format::<f64>(self.0))
     }
 }
 
@@ -103,7 +273,8 @@ impl Eq for LegacyF64 {}
 
 impl Ord for LegacyF64 {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+        // Never fails since there are no NaNs.
+        self.0.partial_cmp(&other.0).unwrap()
     }
 }
 
@@ -113,22 +284,102 @@ impl PartialOrd for LegacyF64 {
     }
 }
 
+impl Hash for LegacyF64 {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // There is neither NaN nor -0.0, so two `LegacyF64`s are equal iff their bits are.
+        self.0.to_bits().hash(state)
+    }
+}
+
+/// Checked addition, see [`checked_add`](struct.LegacyF64.html#method.checked_add).
+///
+/// ```
+/// use ssb_legacy_msg_data::LegacyF64;
+///
+/// let one = LegacyF64::from_f64(1.0).unwrap();
+/// let max = LegacyF64::from_f64(std::f64::MAX).unwrap();
+/// assert_eq!(one + one, LegacyF64::from_f64(2.0));
+/// assert_eq!(max + max, None);
+/// ```
+impl Add for LegacyF64 {
+    type Output = Option<LegacyF64>;
+
+    fn add(self, other: LegacyF64) -> Option<LegacyF64> {
+        self.checked_add(other)
+    }
+}
+
+/// Checked subtraction, see [`checked_sub`](struct.LegacyF64.html#method.checked_sub).
+impl Sub for LegacyF64 {
+    type Output = Option<LegacyF64>;
+
+    fn sub(self, other: LegacyF64) -> Option<LegacyF64> {
+        self.checked_sub(other)
+    }
+}
+
+/// Checked multiplication, see [`checked_mul`](struct.LegacyF64.html#method.checked_mul).
+impl Mul for LegacyF64 {
+    type Output = Option<LegacyF64>;
+
+    fn mul(self, other: LegacyF64) -> Option<LegacyF64> {
+        self.checked_mul(other)
+    }
+}
+
+/// Checked negation, yields `None` when negating zero (since `-0.0` is not a valid
+/// `LegacyF64`).
+impl Neg for LegacyF64 {
+    type Output = Option<LegacyF64>;
+
+    fn neg(self) -> Option<LegacyF64> {
+        LegacyF64::from_f64(-self.0)
+    }
+}
+
 impl From<LegacyF64> for f64 {
     fn from(f: LegacyF64) -> Self {
         f.0
     }
 }
 
+impl Serialize for LegacyF64 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for LegacyF64 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<LegacyF64, D::Error> {
+        let f = f64::deserialize(deserializer)?;
+        LegacyF64::from_f64(f).ok_or_else(|| {
+            de::Error::invalid_value(
+                Unexpected::Float(f),
+                &"a float that is neither an infinity, nor -0.0, nor NaN",
+            )
+        })
+    }
+}
+
+/// The largest integer allowed in ssb data, 2^53 - 1. This is javascript's
+/// `Number.MAX_SAFE_INTEGER`, larger integers can not always be represented exactly
+/// by a 64 bit float.
+pub const MAX_SAFE_INTEGER: u64 = 9007199254740991;
+
+/// The smallest integer allowed in ssb data, -(2^53 - 1). This is javascript's
+/// `Number.MIN_SAFE_INTEGER`.
+pub const MIN_SAFE_INTEGER: i64 = -9007199254740991;
+
 /// Checks whether a given `u64` is allowed for usage in ssb data (it is
-/// not larger than 2^53).
+/// not larger than [`MAX_SAFE_INTEGER`](MAX_SAFE_INTEGER)).
 pub fn is_u64_valid(n: u64) -> bool {
-    n < 9007199254740992
+    n <= MAX_SAFE_INTEGER
 }
 
-/// Checks whether a given `i64` is allowed for usage in ssb data (its
-/// absolute value is not larger than 2^53).
+/// Checks whether a given `i64` is allowed for usage in ssb data (it lies between
+/// [`MIN_SAFE_INTEGER`](MIN_SAFE_INTEGER) and [`MAX_SAFE_INTEGER`](MAX_SAFE_INTEGER)).
 pub fn is_i64_valid(n: i64) -> bool {
-    n < 9007199254740992 && n > -9007199254740992
+    n >= MIN_SAFE_INTEGER && n <= MAX_SAFE_INTEGER as i64
 }
 
 /// An iterator that yields the
@@ -147,6 +398,12 @@ impl<'a> Iterator for WeirdEncodingIterator<'a> {
     fn next(&mut self) -> Option<Self::Item> {
         self.0.next()
     }
+
+    // This can not implement `ExactSizeIterator`: the exact number of utf16 code units of a
+    // string is only known after looking at all of its characters.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
 }
 
 /// Create an owned representation of the
@@ -174,3 +431,252 @@ pub fn legacy_length(s: &str) -> usize {
     }
     len
 }
+
+/// The maximum [length](https://spec.scuttlebutt.nz/feed/datamodel.html#legacy-length-computation)
+/// of a legacy message.
+pub const MAX_MESSAGE_SIZE: usize = 8192;
+
+/// The error returned by [`check_message_size`](fn.check_message_size.html) for messages
+/// longer than [`MAX_MESSAGE_SIZE`](constant.MAX_MESSAGE_SIZE.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooLarge;
+
+impl fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl error::Error for TooLarge {}
+
+/// Returns the [length](https://spec.scuttlebutt.nz/feed/datamodel.html#legacy-length-computation)
+/// of a message, or an error if it exceeds [`MAX_MESSAGE_SIZE`](constant.MAX_MESSAGE_SIZE.html).
+///
+/// Unlike [`legacy_length`](fn.legacy_length.html), this stops looking at the message as soon
+/// as it is known to be too long.
+///
+/// ```
+/// use ssb_legacy_msg_data::{check_message_size, TooLarge, MAX_MESSAGE_SIZE};
+///
+/// assert_eq!(check_message_size("\u{1f600}"), Ok(2));
+/// assert_eq!(check_message_size(&"a".repeat(MAX_MESSAGE_SIZE)), Ok(MAX_MESSAGE_SIZE));
+/// assert_eq!(check_message_size(&"a".repeat(MAX_MESSAGE_SIZE + 1)), Err(TooLarge));
+/// ```
+pub fn check_message_size(msg: &str) -> Result<usize, TooLarge> {
+    // A character never takes more utf16 code units than utf8 bytes.
+    if msg.len() <= MAX_MESSAGE_SIZE {
+        return Ok(legacy_length(msg));
+    }
+
+    let mut len = 0;
+    for c in msg.chars() {
+        len += c.len_utf16();
+        if len > MAX_MESSAGE_SIZE {
+            return Err(TooLarge);
+        }
+    }
+    Ok(len)
+}
+
+#[test]
+fn test_clamp_to_safe_integer() {
+    let clamp = |f: f64| LegacyF64::from_f64(f).unwrap().clamp_to_safe_integer();
+    let max = MAX_SAFE_INTEGER as f64;
+
+    assert_eq!(clamp(0.0), 0);
+    assert_eq!(clamp(0.9), 0);
+    assert_eq!(clamp(-0.9), 0);
+    assert_eq!(clamp(max - 1.0), MAX_SAFE_INTEGER as i64 - 1);
+    assert_eq!(clamp(max), MAX_SAFE_INTEGER as i64);
+    assert_eq!(clamp(max + 1.0), MAX_SAFE_INTEGER as i64);
+    assert_eq!(clamp(-max), MIN_SAFE_INTEGER);
+    assert_eq!(clamp(-max - 1.0), MIN_SAFE_INTEGER);
+    assert_eq!(clamp(f64::MAX), MAX_SAFE_INTEGER as i64);
+    assert_eq!(clamp(f64::MIN), MIN_SAFE_INTEGER);
+}
+
+#[test]
+fn test_ord() {
+    let f = |f: f64| LegacyF64::from_f64(f).unwrap();
+
+    assert!(f(-1.5) < f(0.0));
+    assert!(f(f64::MAX) > f(1e300));
+    assert_eq!(f(2.0).cmp(&f(2.0)), Ordering::Equal);
+}
+
+#[test]
+fn test_weird_encoding_size_hint() {
+    for s in &[
+        "",
+        "a",
+        "\u{e4}\u{20ac}",
+        "\u{1f600}\u{1f600}",
+        "abc\u{1f600}def\u{10ffff}",
+    ] {
+        let mut iter = to_weird_encoding(s);
+        let len = legacy_length(s);
+
+        for remaining in (0..=len).rev() {
+            let (lower, upper) = iter.size_hint();
+            assert!(lower <= remaining);
+            if let Some(upper) = upper {
+                assert!(remaining <= upper);
+            }
+            iter.next();
+        }
+    }
+}
+
+#[test]
+fn test_check_message_size() {
+    // Four utf8 bytes, but only two utf16 code units each.
+    let emoji = "\u{1f600}".repeat(MAX_MESSAGE_SIZE / 2);
+    assert_eq!(check_message_size(&emoji), Ok(MAX_MESSAGE_SIZE));
+    assert_eq!(check_message_size(&(emoji.clone() + "a")), Err(TooLarge));
+
+    // Two utf8 bytes, but only one utf16 code unit each.
+    let umlauts = "\u{e4}".repeat(MAX_MESSAGE_SIZE - 1);
+    assert_eq!(check_message_size(&umlauts), Ok(MAX_MESSAGE_SIZE - 1));
+
+    assert_eq!(check_message_size(""), Ok(0));
+    assert_eq!(
+        check_message_size(&"a".repeat(10 * MAX_MESSAGE_SIZE)),
+        Err(TooLarge)
+    );
+}
+
+#[test]
+fn test_serde() {
+    use serde::de::value::{Error, F64Deserializer};
+    use serde::de::IntoDeserializer;
+
+    let from = |f: f64| {
+        let de: F64Deserializer<Error> = f.into_deserializer();
+        LegacyF64::deserialize(de).ok()
+    };
+
+    assert_eq!(from(1.5), LegacyF64::from_f64(1.5));
+    assert_eq!(from(0.0), LegacyF64::from_f64(0.0));
+    assert_eq!(from(-0.0), None);
+    assert_eq!(from(f64::INFINITY), None);
+    assert_eq!(from(f64::NAN), None);
+
+    let f = LegacyF64::from_f64(-2.5).unwrap();
+    assert_eq!(json::to_string(&f, true).unwrap(), "-2.5");
+    assert_eq!(json::from_slice::<LegacyF64>(b"-2.5").unwrap(), f);
+}
+
+#[test]
+fn test_arithmetic() {
+    let f = |f: f64| LegacyF64::from_f64(f).unwrap();
+    let max = f(f64::MAX);
+    let min = f(f64::MIN);
+
+    assert_eq!(f(1.0) + f(2.5), Some(f(3.5)));
+    assert_eq!(f(1.0) + max, Some(max));
+    assert_eq!(max + max, None);
+
+    assert_eq!(f(1.0) - f(2.5), Some(f(-1.5)));
+    assert_eq!(f(1.0) - f(1.0), Some(f(0.0)));
+    assert_eq!(min - max, None);
+
+    assert_eq!(f(3.0) * f(-0.5), Some(f(-1.5)));
+    assert_eq!(f(0.0) * f(-1.0), None);
+    assert_eq!(max * f(2.0), None);
+
+    assert_eq!(-f(1.5), Some(f(-1.5)));
+    assert_eq!(-max, Some(min));
+    assert_eq!(-f(0.0), None);
+}
+
+#[test]
+fn test_hash() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+
+    fn hash(f: f64) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        LegacyF64::from_f64(f).unwrap().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    assert_eq!(hash(0.0), hash(-0.0 + 0.0));
+    assert_eq!(hash(0.1 + 0.2), hash(0.30000000000000004));
+    assert_eq!(hash(1e300), hash(1e300));
+
+    let set: HashSet<_> = [1.0, 2.0, 1.0, 0.0]
+        .iter()
+        .map(|&f| LegacyF64::from_f64(f).unwrap())
+        .collect();
+    assert_eq!(set.len(), 3);
+}
+
+#[test]
+fn test_constants() {
+    for &f in &[
+        LegacyF64::ZERO,
+        LegacyF64::ONE,
+        LegacyF64::MAX,
+        LegacyF64::MIN,
+    ] {
+        assert!(LegacyF64::is_valid(f.to_f64()));
+    }
+
+    assert!(LegacyF64::ZERO.to_f64().is_sign_positive());
+    assert_eq!(LegacyF64::ZERO, LegacyF64::default());
+    assert_eq!(LegacyF64::ONE + LegacyF64::ONE, LegacyF64::from_f64(2.0));
+    assert_eq!(-LegacyF64::MAX, Some(LegacyF64::MIN));
+}
+
+#[test]
+fn test_from_integers() {
+    let max = MAX_SAFE_INTEGER;
+    let min = MIN_SAFE_INTEGER;
+
+    assert_eq!(LegacyF64::from_u64(0), Some(LegacyF64::ZERO));
+    assert_eq!(
+        LegacyF64::from_u64(max).map(LegacyF64::to_f64),
+        Some(max as f64)
+    );
+    assert_eq!(LegacyF64::from_u64(max + 1), None);
+    assert_eq!(LegacyF64::from_u64(u64::MAX), None);
+
+    assert_eq!(LegacyF64::from_i64(0), Some(LegacyF64::ZERO));
+    assert_eq!(
+        LegacyF64::from_i64(max as i64).map(LegacyF64::to_f64),
+        Some(max as f64)
+    );
+    assert_eq!(LegacyF64::from_i64(max as i64 + 1), None);
+    assert_eq!(
+        LegacyF64::from_i64(min).map(LegacyF64::to_f64),
+        Some(min as f64)
+    );
+    assert_eq!(LegacyF64::from_i64(min - 1), None);
+    assert_eq!(LegacyF64::from_i64(i64::MIN), None);
+
+    // Every accepted integer round-trips.
+    for &n in &[max as i64, max as i64 - 1, min, min + 1] {
+        assert_eq!(LegacyF64::from_i64(n).unwrap().to_f64() as i64, n);
+    }
+}
+
+#[test]
+fn test_display() {
+    for &f in &[
+        0.0,
+        1.0,
+        -1.5,
+        0.1,
+        1e21,
+        1e-7,
+        123456789.0,
+        f64::MAX,
+        f64::MIN,
+    ] {
+        let f = LegacyF64::from_f64(f).unwrap();
+        assert_eq!(f.to_string(), json::to_string(&f, true).unwrap());
+    }
+
+    assert_eq!(LegacyF64::from_f64(1e-7).unwrap().to_string(), "1e-7");
+    assert_eq!(format!("{:?}", LegacyF64::ONE), "1.0");
+}
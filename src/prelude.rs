@@ -0,0 +1,8 @@
+//! Re-exports of the most commonly used items, so that
+//! `use ssb_legacy_msg_data::prelude::*;` covers typical usage.
+
+pub use super::json::{
+    from_slice, from_slice_partial, to_string, to_vec, to_writer, DecodeJsonError, EncodeJsonError,
+};
+pub use super::value::{ContentValue, RidiculousStringMap, Value};
+pub use super::LegacyF64;